@@ -0,0 +1,226 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use std::sync::Arc;
+use t::*;
+
+/// Objects of the California network, taken from the front of each file of
+/// `dataset/objects`
+const CALIFORNIA_OBJECTS: usize = 500;
+
+/// Zone bound on the California network, far below the configured one
+const CALIFORNIA_MAX_DIST: f32 = 2.0;
+
+/// Dataset a benchmark runs on
+struct Fixture {
+    name: &'static str,
+    graph: Graph,
+    queries: Queries,
+}
+
+impl Fixture {
+    fn test01() -> Self {
+        let conf = Arc::new(AppConfig::default());
+        let reader = Reader::new(conf.clone());
+        Fixture {
+            name: "test01",
+            graph: Graph::new(conf).unwrap(),
+            queries: Queries::new(reader.read_query_csv().unwrap()),
+        }
+    }
+
+    /// California road network with objects of one distribution, zones
+    /// over the first four dimensions.
+    fn california(name: &'static str, objects: &str) -> Self {
+        let mut conf = AppConfig {
+            max_dist: CALIFORNIA_MAX_DIST,
+            ..Default::default()
+        };
+        conf.path(String::from("dataset/california/normalized"));
+        conf.object_path(format!("dataset/objects/{}", objects));
+        let conf = Arc::new(conf);
+
+        let reader = Reader::new(conf.clone());
+        let mut graph = Graph::new_empty_object(conf.clone()).unwrap();
+        let objects = reader.read_object_csv().unwrap();
+        for object in objects.into_iter().take(CALIFORNIA_OBJECTS) {
+            graph.insert_object(object);
+        }
+
+        let dimensions: Vec<DimensionIndex> = (1..=conf.max_dim).collect();
+        let queries = (conf.max_dim / 2 + 1..=conf.max_dim)
+            .map(|k| {
+                Arc::new(Query {
+                    id: k as QueryId,
+                    k,
+                    dimensions: dimensions.clone(),
+                    preferences: vec![Preference::Max; dimensions.len()],
+                })
+            })
+            .collect();
+
+        Fixture {
+            name,
+            graph,
+            queries: Queries::new(queries),
+        }
+    }
+
+    fn all() -> Vec<Fixture> {
+        vec![
+            Fixture::test01(),
+            Fixture::california("california/ind", "ind.txt"),
+            Fixture::california("california/cor", "cor.txt"),
+            Fixture::california("california/anticor", "anticor.txt"),
+        ]
+    }
+
+    fn first_object(&self) -> Arc<DataObject> {
+        let mut objects = self.graph.all_objects();
+        objects.sort_by_key(|o| o.id);
+        objects[0].clone()
+    }
+}
+
+fn bfs_min_heap(c: &mut Criterion, fixtures: &[Fixture]) {
+    let mut group = c.benchmark_group("bfs_min_heap");
+    for f in fixtures {
+        let object = f.first_object();
+        group.bench_function(f.name, |b| {
+            b.iter(|| {
+                let mut view = GraphView::new(&f.graph);
+                let start = view.split_objects(std::slice::from_ref(&object))[0];
+                BfsMinHeap::new(&view, start).count()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn dom_traverse(c: &mut Criterion, fixtures: &[Fixture]) {
+    let mut group = c.benchmark_group("dom_traverse");
+    for f in fixtures {
+        let object = f.first_object();
+        let g = f.queries.iter().next().unwrap();
+        group.bench_function(f.name, |b| {
+            b.iter(|| {
+                DomTraverse::dominate_dominated_by(
+                    &f.graph,
+                    object.clone(),
+                    g.dimensions(),
+                    g.preferences(),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Zones of one object, from the smallest k of a group to the greatest.
+fn voronoi_k_sweep(c: &mut Criterion, fixtures: &[Fixture]) {
+    let mut group = c.benchmark_group("voronoi_k_sweep");
+    for f in fixtures {
+        let object = f.first_object();
+        let mut g = f.queries.iter().next().unwrap().clone();
+        let first = g.pop_first().unwrap();
+        group.bench_function(f.name, |b| {
+            b.iter(|| {
+                let mut voronoi = Voronoi::initial_voronoi(
+                    &f.graph,
+                    object.id,
+                    first.k,
+                    g.dimensions(),
+                    g.preferences(),
+                );
+                for q in g.iter() {
+                    voronoi.continue_voronoi(q.k);
+                }
+                voronoi
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Ranges of many objects on the first edge of each fixture, inserted then
+/// removed again.
+fn edge_result(c: &mut Criterion, fixtures: &[Fixture]) {
+    let key = ZoneKey::new(&[1], &[Preference::Max], 1);
+
+    let mut group = c.benchmark_group("edge_result");
+    for f in fixtures {
+        let edges = f.graph.map_edges();
+        let (edge_id, edge) = edges
+            .iter()
+            .min_by_key(|(id, _)| **id)
+            .map(|(id, e)| (*id, e.len))
+            .unwrap();
+        let empty = || ResultVoronoi::from_edge_ids(edges.clone());
+
+        for count in [10, 100, 1000].iter() {
+            let ranges: Vec<Range> = (0..*count)
+                .map(|i| {
+                    let start = edge * (i % 17) as f32 / 17.0;
+                    Range {
+                        start,
+                        end: start + edge / 4.0,
+                        centroid_id: i,
+                    }
+                })
+                .collect();
+
+            let id = BenchmarkId::new(format!("{}/insert", f.name), count);
+            group.bench_with_input(id, &ranges, |b, ranges| {
+                b.iter_batched(
+                    empty,
+                    |mut result| {
+                        result.insert(&key, edge_id, ranges.clone());
+                        result
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+            let id = BenchmarkId::new(format!("{}/remove", f.name), count);
+            group.bench_with_input(id, &ranges, |b, ranges| {
+                b.iter_batched(
+                    || {
+                        let mut result = empty();
+                        result.insert(&key, edge_id, ranges.clone());
+                        result
+                    },
+                    |mut result| {
+                        for r in ranges {
+                            result.remove(r.centroid_id, &key);
+                        }
+                        result
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+/// All zones of each fixture. California fixtures are a sample of
+/// `CALIFORNIA_OBJECTS` objects within `CALIFORNIA_MAX_DIST`, as a single
+/// build of all 10000 objects at the configured `max_dist` takes minutes.
+fn build_sampled(c: &mut Criterion, fixtures: &[Fixture]) {
+    let mut group = c.benchmark_group("build_sampled");
+    group.sample_size(10);
+    for f in fixtures {
+        let builder = ZoneBuilder::new(&f.graph, &f.queries).threads(1).unwrap();
+        group.bench_function(f.name, |b| b.iter(|| builder.build()));
+    }
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    let fixtures = Fixture::all();
+    bfs_min_heap(c, &fixtures);
+    dom_traverse(c, &fixtures);
+    voronoi_k_sweep(c, &fixtures);
+    edge_result(c, &fixtures);
+    build_sampled(c, &fixtures);
+}
+
+criterion_group!(my_benchmark, benches);
+criterion_main!(my_benchmark);
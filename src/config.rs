@@ -1,4 +1,10 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::prelude::*;
 
@@ -6,7 +12,19 @@ use crate::prelude::*;
 #[derive(Clone, Debug)]
 pub struct AppConfig {
     pub max_dim: DimensionIndex,
+    /// Greatest distance from an object to its zone, in the unit of edge
+    /// lengths
     pub max_dist: f32,
+    /// How edge lengths follow from node coordinates
+    pub distance: DistanceModel,
+    /// Weight column of the edge file used as edge length, see
+    /// `Reader::read_edge_csv`
+    pub weight: Option<String>,
+    /// Whether zones follow one-way edges away from or towards objects
+    pub direction: Direction,
+    /// Preference of each dimension, taken from the object file header
+    /// when empty
+    pub preferences: Vec<Preference>,
     pub dataset_dir: PathBuf,
     pub paths: Paths,
 }
@@ -20,13 +38,37 @@ impl Default for AppConfig {
         AppConfig {
             max_dim: 4,
             max_dist: 100.0,
+            distance: DistanceModel::Euclidean,
+            weight: None,
+            direction: Direction::FromObject,
+            preferences: Vec::new(),
             dataset_dir: dataset_dir.to_path_buf(),
             paths: Paths::new(dataset_dir),
         }
     }
 }
 
+/// Content of a TOML config file. Relative paths are relative to the
+/// directory of the file, paths of the dataset files default to the files
+/// in `dataset_dir`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    max_dim: DimensionIndex,
+    max_dist: f32,
+    distance: Option<DistanceModel>,
+    weight: Option<String>,
+    direction: Option<Direction>,
+    preferences: Option<Vec<Preference>>,
+    dataset_dir: Option<PathBuf>,
+    object_path: Option<PathBuf>,
+    node_path: Option<PathBuf>,
+    edge_path: Option<PathBuf>,
+    query_path: Option<PathBuf>,
+}
+
 impl AppConfig {
+    /// Set dataset directory, relative to the source tree.
     pub fn path(&mut self, rel_path: String) {
         let project_path = Path::new(env!("CARGO_MANIFEST_DIR"));
         let dataset_dir = project_path.join(rel_path);
@@ -34,10 +76,92 @@ impl AppConfig {
         self.paths = Paths::new(dataset_dir);
     }
 
+    /// Set object file, relative to the source tree.
     pub fn object_path(&mut self, rel_path: String) {
         let project_path = Path::new(env!("CARGO_MANIFEST_DIR"));
         self.paths.object_path = project_path.join(rel_path)
     }
+
+    /// Load configuration from a TOML file, see `ConfigFile`. The result is
+    /// not validated yet.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file: ConfigFile = toml::from_str(&content).map_err(|source| ConfigError::Toml {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let resolve = |p: PathBuf| base_dir.join(p);
+        let dataset_dir = resolve(file.dataset_dir.unwrap_or_default());
+        let mut paths = Paths::new(dataset_dir.clone());
+        if let Some(p) = file.object_path {
+            paths.object_path = resolve(p);
+        }
+        if let Some(p) = file.node_path {
+            paths.node_path = resolve(p);
+        }
+        if let Some(p) = file.edge_path {
+            paths.edge_path = resolve(p);
+        }
+        if let Some(p) = file.query_path {
+            paths.query_path = resolve(p);
+        }
+
+        Ok(AppConfig {
+            max_dim: file.max_dim,
+            max_dist: file.max_dist,
+            distance: file.distance.unwrap_or_default(),
+            weight: file.weight,
+            direction: file.direction.unwrap_or_default(),
+            preferences: file.preferences.unwrap_or_default(),
+            dataset_dir,
+            paths,
+        })
+    }
+
+    /// Check values and dataset files before reading the dataset. The object
+    /// file must have exactly `max_dim` attribute columns.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_dim == 0 {
+            return Err(ConfigError::InvalidMaxDim(self.max_dim));
+        }
+        if !(self.max_dist > 0.0 && self.max_dist.is_finite()) {
+            return Err(ConfigError::InvalidMaxDist(self.max_dist));
+        }
+        if !self.preferences.is_empty() && self.preferences.len() != self.max_dim as usize {
+            return Err(ConfigError::PreferenceCount {
+                max_dim: self.max_dim,
+                count: self.preferences.len(),
+            });
+        }
+
+        for path in &[
+            &self.paths.node_path,
+            &self.paths.edge_path,
+            &self.paths.object_path,
+        ] {
+            if !path.is_file() {
+                return Err(ConfigError::MissingFile(path.to_path_buf()));
+            }
+        }
+
+        let reader = Reader::new(Arc::new(self.clone()));
+        let columns = reader.object_dimensions(&self.paths.object_path)?;
+        if columns != self.max_dim as usize {
+            return Err(ConfigError::DimensionMismatch {
+                path: self.paths.object_path.clone(),
+                max_dim: self.max_dim,
+                columns,
+            });
+        }
+        reader.preferences()?;
+        reader.weight_column()?;
+        Ok(())
+    }
 }
 
 /// Paths for all files for dataset
@@ -59,3 +183,128 @@ impl Paths {
         }
     }
 }
+
+/// Invalid or unreadable configuration
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Config file cannot be read
+    Io { path: PathBuf, source: io::Error },
+    /// Config file is not valid TOML or misses a field
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    /// `max_dim` must be at least 1
+    InvalidMaxDim(DimensionIndex),
+    /// `max_dist` must be positive
+    InvalidMaxDist(f32),
+    /// `preferences` is given but not for each dimension
+    PreferenceCount {
+        max_dim: DimensionIndex,
+        count: usize,
+    },
+    /// Dataset file does not exist
+    MissingFile(PathBuf),
+    /// Object file cannot be read
+    Reader(ReaderError),
+    /// Attribute columns of the object file differ from `max_dim`
+    DimensionMismatch {
+        path: PathBuf,
+        max_dim: DimensionIndex,
+        columns: usize,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ConfigError::Toml { path, source } => write!(f, "{}: {}", path.display(), source),
+            ConfigError::InvalidMaxDim(d) => write!(f, "max_dim must be at least 1, got {}", d),
+            ConfigError::InvalidMaxDist(d) => write!(f, "max_dist must be positive, got {}", d),
+            ConfigError::PreferenceCount { max_dim, count } => {
+                write!(f, "{} preferences given, but max_dim is {}", count, max_dim)
+            }
+            ConfigError::MissingFile(path) => write!(f, "{}: no such file", path.display()),
+            ConfigError::Reader(e) => write!(f, "{}", e),
+            ConfigError::DimensionMismatch {
+                path,
+                max_dim,
+                columns,
+            } => write!(
+                f,
+                "{}: {} attribute columns, but max_dim is {}",
+                path.display(),
+                columns,
+                max_dim
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Toml { source, .. } => Some(source),
+            ConfigError::Reader(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReaderError> for ConfigError {
+    fn from(e: ReaderError) -> Self {
+        ConfigError::Reader(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("dataset/test01")
+    }
+
+    #[test]
+    fn from_file() {
+        let conf = AppConfig::from_file(&test_dir().join("config.toml")).unwrap();
+        assert_eq!(conf.max_dim, 4);
+        assert_eq!(conf.paths.node_path, test_dir().join("./node.txt"));
+        conf.validate().unwrap();
+
+        let graph = Graph::new(Arc::new(conf)).unwrap();
+        assert_eq!(graph.all_objects().len(), 4);
+    }
+
+    #[test]
+    fn validate() {
+        let mut conf = AppConfig::default();
+        conf.validate().unwrap();
+
+        conf.max_dim = 3;
+        assert!(matches!(
+            conf.validate(),
+            Err(ConfigError::DimensionMismatch { columns: 4, .. })
+        ));
+
+        conf.max_dim = 4;
+        conf.max_dist = 0.0;
+        assert!(matches!(
+            conf.validate(),
+            Err(ConfigError::InvalidMaxDist(_))
+        ));
+
+        conf.max_dist = 100.0;
+        conf.preferences = vec![Preference::Min];
+        assert!(matches!(
+            conf.validate(),
+            Err(ConfigError::PreferenceCount { count: 1, .. })
+        ));
+
+        conf.preferences = Vec::new();
+        conf.paths.node_path = test_dir().join("missing.txt");
+        assert!(matches!(conf.validate(), Err(ConfigError::MissingFile(_))));
+    }
+}
@@ -3,39 +3,43 @@ use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
 use std::fmt;
 
-/// Traverse a graph with BFS feat `min_heap`.
+/// Traverse a graph with BFS feat `min_heap`. One-way edges are traversed
+/// both ways, so every object which may compete with `start` is reached.
 #[derive(Clone)]
 pub struct BfsMinHeap<'a> {
-    graph: &'a Graph,
+    graph: &'a GraphView<'a>,
     max_dist: f32,
     min_heap: BinaryHeap<TraverseState>,
-    cost_map: HashMap<NodeId, f32>,
-    visited: HashMap<NodeId, bool>,
+    cost_map: HashMap<GraphNodeId, f32>,
+    visited: HashMap<GraphNodeId, bool>,
 }
 
 impl<'a> BfsMinHeap<'a> {
     /// Initialize new traversal. What it does?
     ///
-    /// - Set all node cost as f32::MAX.
+    /// - Treat cost of unreached node as f32::MAX.
     /// - Push neighbors of centroid to `min_heap`.
-    pub fn new(graph: &'a Graph, start: NodeId) -> Self {
-        let max_dist = graph.config.max_dist;
+    pub fn new(graph: &'a GraphView<'a>, start: GraphNodeId) -> Self {
+        let max_dist = graph.config().max_dist;
 
-        let mut cost_map: HashMap<NodeId, f32> =
-            graph.nodes().map(|x| (x, std::f32::MAX)).collect();
-
-        *cost_map.get_mut(&start).unwrap() = 0.0;
+        let mut cost_map: HashMap<GraphNodeId, f32> = HashMap::new();
+        cost_map.insert(start, 0.0);
         let mut min_heap = BinaryHeap::new();
-        for node_id in graph.neighbors(start) {
-            let cost = graph.edge_len(start, node_id);
+        for edge in graph.edges(start) {
+            let node_id = edge.other(start);
+            let cost = edge.len;
+            // the shorter of two edges joining the same nodes
+            if cost >= *cost_map.get(&node_id).unwrap_or(&f32::MAX) {
+                continue;
+            }
             min_heap.push(TraverseState {
                 prev_node_id: start,
-                node_id: node_id,
+                node_id,
                 cost,
             });
 
             // replace cost of `node`
-            *cost_map.get_mut(&node_id).unwrap() = cost;
+            cost_map.insert(node_id, cost);
         }
 
         BfsMinHeap {
@@ -46,11 +50,6 @@ impl<'a> BfsMinHeap<'a> {
             visited: HashMap::new(),
         }
     }
-
-    // fn from_object(graph: &'a mut Graph, object: Arc<DataObject>) -> Self {
-    //     let new_node_id = graph.convert_object_as_node(object);
-    //     BfsMinHeap::new(graph, new_node_id)
-    // }
 }
 
 impl<'a> Iterator for BfsMinHeap<'a> {
@@ -65,14 +64,15 @@ impl<'a> Iterator for BfsMinHeap<'a> {
                 ..
             } = state;
 
-            if let Some(_) = self.visited.get(&node_id_src) {
+            if self.visited.contains_key(&node_id_src) {
                 continue;
             }
             self.visited.insert(node_id_src, true);
 
-            for node_id in self.graph.neighbors(node_id_src) {
-                let cost_next = cost + self.graph.edge_len(node_id, node_id_src);
-                let prev_cost = *self.cost_map.get(&node_id).unwrap();
+            for edge in self.graph.edges(node_id_src) {
+                let node_id = edge.other(node_id_src);
+                let cost_next = cost + edge.len;
+                let prev_cost = *self.cost_map.get(&node_id).unwrap_or(&f32::MAX);
                 if cost_next < prev_cost && cost_next < self.max_dist * 2.0 {
                     self.min_heap.push(TraverseState {
                         prev_node_id: node_id_src,
@@ -81,7 +81,7 @@ impl<'a> Iterator for BfsMinHeap<'a> {
                     });
 
                     // replace the cost if `node_index`
-                    *self.cost_map.get_mut(&node_id).unwrap() = cost_next;
+                    self.cost_map.insert(node_id, cost_next);
                 }
             }
 
@@ -106,8 +106,8 @@ impl<'a> fmt::Debug for BfsMinHeap<'a> {
 #[derive(Copy, Clone, Debug)]
 pub struct TraverseState {
     pub cost: f32,
-    pub node_id: NodeId,
-    pub prev_node_id: NodeId,
+    pub node_id: GraphNodeId,
+    pub prev_node_id: GraphNodeId,
 }
 
 impl Ord for TraverseState {
@@ -116,10 +116,11 @@ impl Ord for TraverseState {
             panic!("TraverseState.cost shouldn't be a NaN!");
         }
 
+        // reversed, so `BinaryHeap` pops the smallest cost first
         if self.cost < other.cost {
-            Ordering::Less
-        } else if self.cost > other.cost {
             Ordering::Greater
+        } else if self.cost > other.cost {
+            Ordering::Less
         } else {
             Ordering::Equal
         }
@@ -128,7 +129,7 @@ impl Ord for TraverseState {
 
 impl PartialOrd for TraverseState {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        other.cost.partial_cmp(&self.cost)
+        Some(self.cmp(other))
     }
 }
 
@@ -151,13 +152,14 @@ mod tests {
     #[test]
     fn bfs_min_heap_new() {
         let conf = Arc::new(AppConfig::default());
-        let graph = Graph::new(conf);
-        let mut bfs = BfsMinHeap::new(&graph, 1);
+        let graph = Graph::new(conf).unwrap();
+        let view = GraphView::new(&graph);
+        let mut bfs = BfsMinHeap::new(&view, GraphNodeId::Original(1));
 
         let node_id_orders = [2, 3, 4, 6, 5];
         for node_id in node_id_orders.iter() {
             let state = bfs.next().unwrap();
-            assert_eq!(state.node_id, *node_id);
+            assert_eq!(state.node_id, GraphNodeId::Original(*node_id));
         }
     }
 }
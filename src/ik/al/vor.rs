@@ -1,11 +1,11 @@
 use super::bfs_mh::BfsMinHeap;
 use super::vor_mh::{TraverseState as State, VoronoiMinHeap};
 use crate::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 pub struct Voronoi<'a> {
-    scope: HashMap<EdgeId, Vec<Range>>,
+    scope: HashMap<GraphEdgeId, Vec<Range>>,
     min_heap: VoronoiMinHeap<'a>,
     max_distance: f32,
     start_centroid_id: CentroidId,
@@ -14,21 +14,43 @@ pub struct Voronoi<'a> {
 impl<'a> Voronoi<'a> {
     // TODO: DONE graph should be owned or cloned
     // TODO: DONE add parameter k
-    pub fn initial_voronoi(graph: &'a mut Graph, object_id: ObjectId, k_start: K) -> Self {
+    /// Compute voronoi of `object_id` for `k_start`, where dominance is
+    /// only considered over `dimensions`, with `preferences` of each.
+    pub fn initial_voronoi(
+        graph: &'a Graph,
+        object_id: ObjectId,
+        k_start: K,
+        dimensions: &[DimensionIndex],
+        preferences: &[Preference],
+    ) -> Self {
+        let dom_traverse =
+            DomTraverse::dominate_dominated_by_from_id(graph, object_id, dimensions, preferences);
+        Self::from_dom_traverse(graph, &dom_traverse, k_start, dimensions.len() as K)
+    }
+
+    /// Compute voronoi of the originator of `dom_traverse` for `k_start`,
+    /// `max_k` being the number of dimensions `dom_traverse` compared.
+    pub fn from_dom_traverse(
+        graph: &'a Graph,
+        dom_traverse: &DomTraverse,
+        k_start: K,
+        max_k: K,
+    ) -> Self {
         let max_distance = graph.config.max_dist;
-        let dom_traverse = DomTraverse::dominate_dominated_by_from_id(graph, object_id);
+        let object_id = dom_traverse.originator.id;
         let mut dominated_by_vec = dom_traverse.dominated_by_objects(k_start);
         dominated_by_vec.push(object_id);
-        let centroid_ids = graph.convert_object_ids_to_node(dominated_by_vec);
+        let mut view = GraphView::new(graph);
+        view.split_object_ids(&dominated_by_vec);
         let mut map_objects_k = dom_traverse.map_dominated_by_objects_k(k_start);
-        map_objects_k.insert(object_id, graph.config.max_dim);
-        let min_heap = VoronoiMinHeap::new(graph, centroid_ids, map_objects_k, k_start);
+        map_objects_k.insert(object_id, max_k);
+        let min_heap = VoronoiMinHeap::new(view, dominated_by_vec, map_objects_k, k_start, max_k);
 
         let mut voronoi = Self {
             scope: HashMap::new(),
             min_heap,
             max_distance,
-            start_centroid_id: Graph::as_centroid_id(object_id),
+            start_centroid_id: object_id,
         };
         voronoi.compute_scope();
         voronoi.convert_voronoi_scope_to_original_edge();
@@ -52,7 +74,11 @@ impl<'a> Voronoi<'a> {
                 edge,
             } = state;
             let edge = edge.unwrap();
-            if centroid_pt_in_ne == 0 || centroid_ct_in_ns == centroid_pt_in_ne {
+            // a one-way edge is only entered at node start, its owner takes
+            // all of it
+            let is_contested =
+                !edge.oneway && centroid_pt_in_ne.is_some_and(|pt| pt != centroid_ct_in_ns);
+            if !is_contested {
                 if centroid_ct_in_ns != self.start_centroid_id {
                     continue;
                 }
@@ -75,6 +101,25 @@ impl<'a> Voronoi<'a> {
                         centroid_id: centroid_ct_in_ns,
                     };
                     Self::add_scope_itself(&mut scope, range, edge.id);
+
+                    // the centroid may reach node end by a shorter path
+                    if !edge.oneway && centroid_pt_in_ne.is_some() && cost_pt_to_ne < max_distance {
+                        let reach = max_distance - cost_pt_to_ne;
+                        let range = if edge.ni == start_node_id {
+                            Range {
+                                start: edge.len - reach,
+                                end: edge.len,
+                                centroid_id: centroid_ct_in_ns,
+                            }
+                        } else {
+                            Range {
+                                start: 0.0,
+                                end: reach,
+                                centroid_id: centroid_ct_in_ns,
+                            }
+                        };
+                        Self::add_scope_itself(&mut scope, range, edge.id);
+                    }
                 } else {
                     let range = Range {
                         start: 0.0,
@@ -84,42 +129,47 @@ impl<'a> Voronoi<'a> {
                     Self::add_scope_itself(&mut scope, range, edge.id);
                 }
             } else {
-                let center_dist =
-                    ((cost_ct_to_ns + cost_pt_to_ne + edge.len) / 2.0) - cost_ct_to_ns;
+                // split the edge at the middle of both centroids, each side
+                // no farther than `max_distance` from its centroid
+                let center_dist = (((cost_ct_to_ns + cost_pt_to_ne + edge.len) / 2.0)
+                    - cost_ct_to_ns)
+                    .max(0.0)
+                    .min(edge.len);
+                let ct_end = center_dist.min(max_distance - cost_ct_to_ns);
+                let pt_start = center_dist.max(edge.len - (max_distance - cost_pt_to_ne));
                 if edge.ni == start_node_id {
                     if centroid_ct_in_ns == self.start_centroid_id {
                         let range = Range {
                             start: 0.0,
-                            end: center_dist,
+                            end: ct_end,
                             centroid_id: centroid_ct_in_ns,
                         };
                         Self::add_scope_itself(&mut scope, range, edge.id);
                     }
 
-                    if centroid_pt_in_ne == self.start_centroid_id {
+                    if centroid_pt_in_ne == Some(self.start_centroid_id) {
                         let range = Range {
-                            start: center_dist,
+                            start: pt_start,
                             end: edge.len,
-                            centroid_id: centroid_pt_in_ne,
+                            centroid_id: self.start_centroid_id,
                         };
                         Self::add_scope_itself(&mut scope, range, edge.id);
                     }
                 } else {
-                    let c = edge.len - center_dist;
                     if centroid_ct_in_ns == self.start_centroid_id {
                         let range = Range {
-                            start: c,
+                            start: edge.len - ct_end,
                             end: edge.len,
                             centroid_id: centroid_ct_in_ns,
                         };
                         Self::add_scope_itself(&mut scope, range, edge.id);
                     }
 
-                    if centroid_pt_in_ne == self.start_centroid_id {
+                    if centroid_pt_in_ne == Some(self.start_centroid_id) {
                         let range = Range {
                             start: 0.0,
-                            end: c,
-                            centroid_id: centroid_pt_in_ne,
+                            end: edge.len - pt_start,
+                            centroid_id: self.start_centroid_id,
                         };
                         Self::add_scope_itself(&mut scope, range, edge.id);
                     }
@@ -142,7 +192,7 @@ impl<'a> Voronoi<'a> {
     }
 
     #[allow(dead_code)]
-    fn add_scope(&mut self, range: Range, edge_id: EdgeId) {
+    fn add_scope(&mut self, range: Range, edge_id: GraphEdgeId) {
         if let Some(ranges) = self.scope.get_mut(&edge_id) {
             ranges.push(range);
         } else {
@@ -150,7 +200,11 @@ impl<'a> Voronoi<'a> {
         }
     }
 
-    fn add_scope_itself(scope: &mut HashMap<EdgeId, Vec<Range>>, range: Range, edge_id: EdgeId) {
+    fn add_scope_itself(
+        scope: &mut HashMap<GraphEdgeId, Vec<Range>>,
+        range: Range,
+        edge_id: GraphEdgeId,
+    ) {
         if let Some(ranges) = scope.get_mut(&edge_id) {
             ranges.push(range);
         } else {
@@ -158,41 +212,37 @@ impl<'a> Voronoi<'a> {
         }
     }
 
+    /// Move ranges of the edges split at centroids back to the original
+    /// edge, shifting each by the offset its split edge starts at.
     fn convert_voronoi_scope_to_original_edge(&mut self) {
         let map_new_edge = self.min_heap.map_new_edge();
         for (edge_id, vec_new_edge_id) in map_new_edge {
-            let mut adjusted_scopes: HashMap<CentroidId, Range> = HashMap::new();
+            let mut ranges = Vec::new();
+            let mut offset = 0.0;
             for new_edge_id in vec_new_edge_id {
-                let scopes = self.scope.get(&new_edge_id);
-                if scopes.is_none() {
-                    break;
-                }
-                let mut scopes = scopes.unwrap().clone();
-                scopes.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
-                for scope in scopes {
-                    if let Some(s) = adjusted_scopes.get_mut(&scope.centroid_id) {
-                        s.end = s.end + scope.end;
-                    } else {
-                        let new_scope = Range {
-                            start: scope.start,
-                            end: scope.start + (scope.end - scope.start),
+                let len = self.min_heap.edge_len(new_edge_id);
+                if let Some(scopes) = self.scope.get(&new_edge_id) {
+                    for scope in scopes {
+                        ranges.push(Range {
+                            start: offset + scope.start,
+                            end: offset + scope.end,
                             centroid_id: scope.centroid_id,
-                        };
-                        adjusted_scopes.insert(scope.centroid_id, new_scope);
+                        });
                     }
                 }
+                offset += len;
             }
-            let ranges: Vec<Range> = adjusted_scopes.values().map(|v| v.clone()).collect();
+            let edge_id = GraphEdgeId::Original(edge_id);
             self.scope.remove(&edge_id);
             self.scope.insert(edge_id, ranges);
         }
     }
 
     // TODO: DONE call to save
-    pub fn save_to_result(&self, result: &mut ResultVoronoi, k: K) {
+    pub fn save_to_result(&self, result: &mut ResultVoronoi, key: &ZoneKey) {
         for (edge_id, ranges) in &self.scope {
-            if self.min_heap.is_original_edge(*edge_id) {
-                result.insert(k, *edge_id, ranges.clone());
+            if let GraphEdgeId::Original(edge_id) = *edge_id {
+                result.insert(key, edge_id, ranges.clone());
             }
         }
     }
@@ -213,66 +263,114 @@ pub struct DomTraverse {
 }
 
 impl DomTraverse {
-    /// Get objects dominate and dominated by originator.
-    pub fn dominate_dominated_by(graph: &mut Graph, originator: Arc<DataObject>) -> Self {
-        let centroid_id = graph.convert_object_as_node(originator.clone());
-        let bfs = BfsMinHeap::new(graph, centroid_id);
-
-        let mut dominated_by: HashMap<K, Vec<ObjectId>> = HashMap::new();
-        let mut dominate: HashMap<K, Vec<ObjectId>> = HashMap::new();
-
-        for TraverseState {
-            node_id,
-            prev_node_id,
-            ..
-        } in bfs
-        {
-            let objects = graph.objects(node_id, prev_node_id);
-            for object in objects {
-                let mut src_score = 0;
-                let mut dst_score = 0;
-                for (i, src_val) in originator.attr.iter().enumerate() {
-                    let dst_val = object.attr.get(i).unwrap();
-                    if src_val > dst_val {
-                        src_score += 1;
-                    } else if src_val < dst_val {
-                        dst_score += 1;
-                    } else {
-                        src_score += 1;
-                        dst_score += 1;
-                    }
-                }
-
-                if src_score > dst_score {
-                    let k = src_score;
-                    if let Some(a) = dominate.get_mut(&k) {
-                        a.push(object.id);
-                    } else {
-                        dominate.insert(k, vec![object.id]);
-                    }
-                } else if src_score < dst_score {
-                    let k = dst_score;
-                    if let Some(a) = dominated_by.get_mut(&k) {
-                        a.push(object.id);
-                    } else {
-                        dominated_by.insert(k, vec![object.id]);
-                    }
-                }
-            }
+    /// Get objects dominate and dominated by originator, comparing only
+    /// the attributes in `dimensions`, with `preferences` of each.
+    pub fn dominate_dominated_by(
+        graph: &Graph,
+        originator: Arc<DataObject>,
+        dimensions: &[DimensionIndex],
+        preferences: &[Preference],
+    ) -> Self {
+        let objects = Self::neighborhood(graph, &originator);
+        let mut dom_traverse = Self::new(originator);
+        for object in objects {
+            let (src_score, dst_score) =
+                Self::compare(&dom_traverse.originator, &object, dimensions, preferences);
+            dom_traverse.add(object.id, src_score, dst_score);
         }
+        dom_traverse
+    }
 
-        graph.remove_node(centroid_id);
+    pub fn originator(&self) -> &Arc<DataObject> {
+        &self.originator
+    }
 
+    pub(crate) fn new(originator: Arc<DataObject>) -> Self {
         DomTraverse {
             originator,
-            dominated_by,
-            dominate,
+            dominated_by: HashMap::new(),
+            dominate: HashMap::new(),
+        }
+    }
+
+    /// Objects to compare originator with: objects of every edge around
+    /// the nodes reached from it, not only the edges of the shortest paths.
+    pub(crate) fn neighborhood(
+        graph: &Graph,
+        originator: &Arc<DataObject>,
+    ) -> Vec<Arc<DataObject>> {
+        let mut view = GraphView::new(graph);
+        let centroid_id = *view
+            .split_objects(std::slice::from_ref(originator))
+            .first()
+            .unwrap();
+        let bfs = BfsMinHeap::new(&view, centroid_id);
+
+        let mut seen = HashSet::new();
+        let mut neighborhood = Vec::new();
+        for TraverseState { node_id, .. } in bfs {
+            let objects = view
+                .edges(node_id)
+                .into_iter()
+                .flat_map(|e| e.objects.iter().cloned());
+            for object in objects {
+                if object.id == originator.id || !seen.insert(object.id) {
+                    continue;
+                }
+                neighborhood.push(object);
+            }
         }
+        neighborhood
     }
 
-    fn dominate_dominated_by_from_id(graph: &mut Graph, object_id: ObjectId) -> Self {
+    /// Record `object_id` by the number of dimensions in which originator
+    /// is not worse, `src_score`, and it is not worse, `dst_score`.
+    pub(crate) fn add(&mut self, object_id: ObjectId, src_score: K, dst_score: K) {
+        if src_score > dst_score {
+            self.dominate.entry(src_score).or_default().push(object_id);
+        } else if src_score < dst_score {
+            self.dominated_by
+                .entry(dst_score)
+                .or_default()
+                .push(object_id);
+        }
+    }
+
+    fn dominate_dominated_by_from_id(
+        graph: &Graph,
+        object_id: ObjectId,
+        dimensions: &[DimensionIndex],
+        preferences: &[Preference],
+    ) -> Self {
         let object = graph.object(object_id);
-        Self::dominate_dominated_by(graph, object)
+        Self::dominate_dominated_by(graph, object, dimensions, preferences)
+    }
+
+    /// Count in how many of `dimensions` `src` is not worse than `dst`, and
+    /// vice versa. Dimensions are 1-based, `1` being the first attribute.
+    fn compare(
+        src: &DataObject,
+        dst: &DataObject,
+        dimensions: &[DimensionIndex],
+        preferences: &[Preference],
+    ) -> (K, K) {
+        debug_assert_eq!(dimensions.len(), preferences.len());
+        let mut src_score = 0;
+        let mut dst_score = 0;
+        for (d, preference) in dimensions.iter().zip(preferences) {
+            let i = (*d - 1) as usize;
+            let src_val = *src.attr.get(i).unwrap();
+            let dst_val = *dst.attr.get(i).unwrap();
+            if preference.is_better(src_val, dst_val) {
+                src_score += 1;
+            } else if preference.is_better(dst_val, src_val) {
+                dst_score += 1;
+            } else {
+                src_score += 1;
+                dst_score += 1;
+            }
+        }
+        (src_score, dst_score)
     }
 
     fn dominated_by_objects(&self, k_start: K) -> Vec<ObjectId> {
@@ -328,24 +426,67 @@ mod tests {
     #[test]
     fn dom_traverse_test() {
         let conf = Arc::new(AppConfig::default());
-        let mut graph = Graph::new(conf);
+        let graph = Graph::new(conf).unwrap();
         let object_id = 3;
-        let result = DomTraverse::dominate_dominated_by_from_id(&mut graph, object_id);
+        let result = DomTraverse::dominate_dominated_by_from_id(
+            &graph,
+            object_id,
+            &[1, 2, 3, 4],
+            &[Preference::Max; 4],
+        );
         assert_eq!(result.dominate.get(&3).unwrap().len(), 1);
         assert_eq!(result.dominated_by.get(&3).unwrap().len(), 1);
 
         println!("{:#?}", result);
     }
 
+    #[test]
+    fn dom_traverse_subspace_test() {
+        let conf = Arc::new(AppConfig::default());
+        let graph = Graph::new(conf).unwrap();
+        let object_id = 3;
+        let result = DomTraverse::dominate_dominated_by_from_id(
+            &graph,
+            object_id,
+            &[1, 3],
+            &[Preference::Max; 2],
+        );
+        assert_eq!(result.dominate.get(&2).unwrap().len(), 2);
+        assert!(!result.dominate.contains_key(&3));
+        assert!(result.dominated_by.is_empty());
+
+        let min = [Preference::Min; 2];
+        let result = DomTraverse::dominate_dominated_by_from_id(&graph, object_id, &[1, 3], &min);
+        assert_eq!(result.dominated_by.get(&2).unwrap().len(), 2);
+        assert!(result.dominate.is_empty());
+    }
+
+    #[test]
+    fn dom_traverse_non_tree_edge() {
+        // object 2 is on the edge closing the cycle, off every shortest path
+        let graph = regression_graph("non_tree_edge");
+        let result = DomTraverse::dominate_dominated_by_from_id(
+            &graph,
+            1,
+            &[1, 2, 3, 4],
+            &[Preference::Max; 4],
+        );
+        assert_eq!(result.dominated_by.get(&4), Some(&vec![2]));
+    }
+
     #[test]
     fn voronoi_test() {
         let conf = Arc::new(AppConfig::default());
-        let mut graph = Graph::new(conf);
+        let graph = Graph::new(conf).unwrap();
         let object_id = 2;
-        let mut voronoi = Voronoi::initial_voronoi(&mut graph, object_id, 3);
+        let mut voronoi =
+            Voronoi::initial_voronoi(&graph, object_id, 3, &[1, 2, 3, 4], &[Preference::Max; 4]);
         println!("{:#?}", voronoi.scope);
 
-        let tests = [(200002, 1), (300002, 1)];
+        let tests = [
+            (GraphEdgeId::BeforeObject(2), 1),
+            (GraphEdgeId::AfterObject(2), 1),
+        ];
         for (edge_id, range_len) in tests.iter() {
             let mut is_exist = false;
             for (e, ranges) in &voronoi.scope {
@@ -358,6 +499,76 @@ mod tests {
 
         voronoi.continue_voronoi(4);
         println!("{:#?}", voronoi.scope);
-        println!("");
+        println!();
+    }
+
+    fn regression_graph(name: &str) -> Graph {
+        let mut conf = AppConfig::default();
+        conf.path(format!("dataset/regression/{}", name));
+        Graph::new(Arc::new(conf)).unwrap()
+    }
+
+    /// Assert the zone of the originator of `voronoi` on original edge
+    /// `edge_id`, ranges merged and sorted.
+    fn assert_zone(voronoi: &Voronoi, edge_id: EdgeId, expected: &[(f32, f32)]) {
+        let mut ranges: Vec<(f32, f32)> = voronoi
+            .scope
+            .get(&GraphEdgeId::Original(edge_id))
+            .into_iter()
+            .flatten()
+            .filter(|r| r.centroid_id == voronoi.start_centroid_id && r.start < r.end)
+            .map(|r| (r.start, r.end))
+            .collect();
+        ranges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut zone: Vec<(f32, f32)> = Vec::new();
+        for (start, end) in ranges {
+            match zone.last_mut() {
+                Some(last) if start <= last.1 + 1e-3 => last.1 = last.1.max(end),
+                _ => zone.push((start, end)),
+            }
+        }
+
+        let is_equal = zone.len() == expected.len()
+            && zone
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3);
+        assert!(is_equal, "zone of edge {}: {:?}", edge_id, zone);
+    }
+
+    #[test]
+    fn voronoi_split_edge() {
+        // object 1 at 70 and its dominator 2 at 20 of the same edge
+        let graph = regression_graph("split_edge");
+        let voronoi = Voronoi::initial_voronoi(&graph, 1, 3, &[1, 2, 3, 4], &[Preference::Max; 4]);
+        assert_zone(&voronoi, 1, &[(45.0, 100.0)]);
+    }
+
+    #[test]
+    fn voronoi_cycle() {
+        // both ends of edge 3 are reached by object 1 through node 1
+        let graph = regression_graph("cycle");
+        let voronoi = Voronoi::initial_voronoi(&graph, 1, 3, &[1, 2, 3, 4], &[Preference::Max; 4]);
+        assert_zone(&voronoi, 3, &[(0.0, 35.0), (37.0, 72.0)]);
+    }
+
+    #[test]
+    fn voronoi_growing_k() {
+        // object 2 dominates object 1 only for k = 3
+        let graph = regression_graph("growing_k");
+        let mut voronoi =
+            Voronoi::initial_voronoi(&graph, 1, 3, &[1, 2, 3, 4], &[Preference::Max; 4]);
+        assert_zone(&voronoi, 2, &[(0.0, 5.0)]);
+        voronoi.continue_voronoi(4);
+        assert_zone(&voronoi, 2, &[(0.0, 50.0)]);
+    }
+
+    #[test]
+    fn voronoi_contested_within_max_dist() {
+        // object 1 and its dominator 2 reach both ends of edge 3 at 85, the
+        // middle of the edge is 130 away from them
+        let graph = regression_graph("max_dist");
+        let voronoi = Voronoi::initial_voronoi(&graph, 1, 3, &[1, 2, 3, 4], &[Preference::Max; 4]);
+        assert_zone(&voronoi, 3, &[(0.0, 15.0)]);
     }
 }
@@ -4,40 +4,41 @@ use std::collections::{BinaryHeap, HashMap, HashSet};
 
 // TODO: DONE save k value
 pub struct VoronoiMinHeap<'a> {
-    graph: &'a Graph,
+    graph: GraphView<'a>,
     max_dist: f32,
     min_heap: BinaryHeap<TraverseState>,
-    cost_map: HashMap<NodeId, (CentroidId, f32)>,
-    visited: HashSet<(NodeId, NodeId)>,
+    cost_map: HashMap<GraphNodeId, (CentroidId, f32)>,
+    visited: HashSet<GraphEdgeId>,
     map_object_id_k: HashMap<ObjectId, K>,
-    map_centroid_edge_id: HashMap<EdgeId, (CentroidId, K)>,
+    map_centroid_edge_id: HashMap<GraphEdgeId, (CentroidId, K)>,
     min_heap_reserve: Vec<TraverseState>,
     is_initial: bool,
+    max_k: K,
     pub current_k: K,
 }
 
 impl<'a> VoronoiMinHeap<'a> {
     pub fn new(
-        graph: &'a mut Graph,
+        graph: GraphView<'a>,
         centroid_ids: Vec<CentroidId>,
         map_object_id_k: HashMap<ObjectId, K>,
         start_k: K,
+        max_k: K,
     ) -> Self {
         let mut min_heap = BinaryHeap::new();
         let mut cost_map = HashMap::new();
         for centroid_id in centroid_ids {
-            for node_id in graph.neighbors(centroid_id) {
-                let edge = graph.edge(node_id, centroid_id).unwrap();
-                let smallest_k = *map_object_id_k
-                    .get(&Graph::as_object_id(centroid_id))
-                    .unwrap();
+            let centroid_node_id = GraphNodeId::Object(centroid_id);
+            for edge in graph.next_edges(centroid_node_id) {
+                let node_id = edge.other(centroid_node_id);
+                let smallest_k = *map_object_id_k.get(&centroid_id).unwrap();
                 min_heap.push(TraverseState {
                     cost_ct_to_ns: 0.0,
                     cost_ct_to_ne: edge.len,
                     cost_pt_to_ne: 0.0,
                     centroid_ct_in_ns: centroid_id,
-                    centroid_pt_in_ne: 0,
-                    start_node_id: centroid_id,
+                    centroid_pt_in_ne: None,
+                    start_node_id: centroid_node_id,
                     end_node_id: node_id,
                     smallest_k: (smallest_k, Position::Start),
                     edge: SimpleEdge::from_some(Some(edge)),
@@ -50,13 +51,13 @@ impl<'a> VoronoiMinHeap<'a> {
                 } else {
                     cost_map.insert(node_id, (centroid_id, edge.len));
                 }
-                cost_map.insert(centroid_id, (centroid_id, 0.0));
+                cost_map.insert(centroid_node_id, (centroid_id, 0.0));
             }
         }
 
         VoronoiMinHeap {
+            max_dist: graph.config().max_dist,
             graph,
-            max_dist: graph.config.max_dist,
             min_heap,
             cost_map,
             visited: HashSet::new(),
@@ -64,107 +65,106 @@ impl<'a> VoronoiMinHeap<'a> {
             map_centroid_edge_id: HashMap::new(),
             min_heap_reserve: Vec::new(),
             is_initial: true,
+            max_k,
             current_k: start_k,
         }
     }
 
-    /// Pop min_heap_reverse to min_heap
+    /// Pop min_heap_reverse to min_heap. States next to a centroid that no
+    /// longer k-dominates for `current_k` are traversed again from the
+    /// centroid on the other side of the edge.
     #[allow(dead_code)]
     pub fn pop_min_heap_reserve(&mut self) {
-        let mut min_heap: BinaryHeap<TraverseState> = BinaryHeap::new();
-        let min_heap_reserve: Vec<TraverseState> = self
+        let current_k = self.current_k;
+        let (popped, reserved): (Vec<TraverseState>, Vec<TraverseState>) = self
             .min_heap_reserve
             .iter()
-            .filter(|t| {
-                if t.smallest_k.0 <= self.current_k {
-                    min_heap.push(**t);
-                }
-
-                t.smallest_k.0 > self.current_k
-            })
-            .map(|t| *t)
-            .collect();
-
-        self.min_heap_reserve = min_heap_reserve;
-        self.min_heap = min_heap
-            .iter()
-            .map(|t| {
-                let mut t = *t;
-                match t.smallest_k.1 {
-                    Position::Start => {
-                        self.remove_cost(t.end_node_id);
-                        t.centroid_pt_in_ne = t.centroid_ct_in_ns;
-                        t.cost_pt_to_ne = t.cost_ct_to_ne;
-
-                        t
-                    }
-                    Position::End => {
-                        self.remove_cost(t.start_node_id);
-                        TraverseState {
-                            cost_ct_to_ns: t.cost_pt_to_ne,
-                            cost_ct_to_ne: t.cost_pt_to_ne,
-                            cost_pt_to_ne: t.cost_pt_to_ne + t.edge.unwrap().len,
-                            centroid_ct_in_ns: t.centroid_pt_in_ne,
-                            centroid_pt_in_ne: t.centroid_pt_in_ne,
-                            start_node_id: t.end_node_id,
-                            end_node_id: t.start_node_id,
-                            smallest_k: t.smallest_k,
-                            edge: t.edge,
-                        }
+            .partition(|t| t.smallest_k.0 < current_k);
+        self.min_heap_reserve = reserved;
+
+        let mut states = Vec::new();
+        for t in popped {
+            match t.smallest_k.1 {
+                // centroid at node end is removed
+                Position::End => {
+                    if self.k_of_object(t.centroid_ct_in_ns) < current_k {
+                        continue;
                     }
+                    self.remove_cost(t.end_node_id);
+                    states.push(TraverseState {
+                        centroid_pt_in_ne: None,
+                        cost_pt_to_ne: 0.0,
+                        ..t
+                    });
                 }
-            })
-            .collect();
+                // centroid at node start is removed, the centroid at node
+                // end cannot go back over a one-way edge
+                Position::Start if t.edge.unwrap().oneway => continue,
+                Position::Start => {
+                    let centroid_id = match t.centroid_pt_in_ne {
+                        Some(c) if self.k_of_object(c) >= current_k => c,
+                        _ => continue,
+                    };
+                    self.remove_cost(t.start_node_id);
+                    states.push(TraverseState {
+                        cost_ct_to_ns: t.cost_pt_to_ne,
+                        cost_ct_to_ne: t.cost_pt_to_ne + t.edge.unwrap().len,
+                        cost_pt_to_ne: 0.0,
+                        centroid_ct_in_ns: centroid_id,
+                        centroid_pt_in_ne: None,
+                        start_node_id: t.end_node_id,
+                        end_node_id: t.start_node_id,
+                        smallest_k: t.smallest_k,
+                        edge: t.edge,
+                    });
+                }
+            }
+        }
+
+        // claim the freed nodes again, closest centroid first
+        let mut min_heap = BinaryHeap::new();
+        for t in states {
+            let is_closer = self
+                .cost(t.end_node_id)
+                .is_none_or(|(_, cost)| t.cost_ct_to_ne < cost);
+            if is_closer {
+                self.cost_map
+                    .insert(t.end_node_id, (t.centroid_ct_in_ns, t.cost_ct_to_ne));
+            }
+            min_heap.push(t);
+        }
+        self.min_heap = min_heap;
     }
 
-    // pub fn from_objects(graph: &'a mut Graph, centroids: Vec<Arc<DataObject>>) -> Self {
-    //     graph.convert_objects_to_node(centroids);
-    //     let mut centroid_ids = Vec::new();
-    //     for (_edge_id, mut node_ids) in graph.map_new_node() {
-    //         centroid_ids.append(&mut node_ids);
-    //     }
-    //     Self::new(graph, centroid_ids)
-    // }
+    /// Centroid and cost of `node_id`, if the centroid still k-dominates for
+    /// `current_k`.
+    fn cost(&self, node_id: GraphNodeId) -> Option<(CentroidId, f32)> {
+        self.cost_map
+            .get(&node_id)
+            .filter(|(c, _)| self.k_of_object(*c) >= self.current_k)
+            .copied()
+    }
 
     /// Return true if already visited, if not visit it and return false.
-    fn visit(&mut self, a: NodeId, b: NodeId) -> bool {
-        let (x, y) = if a < b { (a, b) } else { (b, a) };
-
-        if self.visited.get(&(x, y)).is_some() {
-            return true;
-        } else {
-            self.visited.insert((x, y));
-        }
-
-        return false;
+    fn visit(&mut self, edge_id: GraphEdgeId) -> bool {
+        !self.visited.insert(edge_id)
     }
 
     /// Return true is already visited.
-    fn is_visited(&self, a: NodeId, b: NodeId) -> bool {
-        let (x, y) = if a < b { (a, b) } else { (b, a) };
-
-        if self.visited.get(&(x, y)).is_some() {
-            return true;
-        }
-
-        false
+    fn is_visited(&self, edge_id: GraphEdgeId) -> bool {
+        self.visited.contains(&edge_id)
     }
 
     fn save_map_object_id_to_k(
         &mut self,
         edge: SimpleEdge,
-        s: NodeId,
-        e: NodeId,
+        s: GraphNodeId,
+        e: GraphNodeId,
         curr: CentroidId,
-        prev: CentroidId,
+        prev: Option<CentroidId>,
     ) {
-        let curr = Graph::as_object_id(curr);
-        let prev = Graph::as_object_id(prev);
         if s != e {
-            if prev == 0 {
-                let k_curr = self.map_object_id_k.get(&curr).unwrap();
-                self.map_centroid_edge_id.insert(edge.id, (curr, *k_curr));
-            } else {
+            if let Some(prev) = prev {
                 let k_curr = self.map_object_id_k.get(&curr).unwrap();
                 let k_prev = self.map_object_id_k.get(&prev).unwrap();
 
@@ -173,6 +173,9 @@ impl<'a> VoronoiMinHeap<'a> {
                 } else {
                     self.map_centroid_edge_id.insert(edge.id, (prev, *k_prev));
                 }
+            } else {
+                let k_curr = self.map_object_id_k.get(&curr).unwrap();
+                self.map_centroid_edge_id.insert(edge.id, (curr, *k_curr));
             }
         }
     }
@@ -182,20 +185,25 @@ impl<'a> VoronoiMinHeap<'a> {
             return;
         }
 
-        if state.centroid_ct_in_ns != state.centroid_pt_in_ne && state.smallest_k.0 > self.current_k
+        if state.centroid_pt_in_ne != Some(state.centroid_ct_in_ns)
+            && state.smallest_k.0 >= self.current_k
+            && (self.k_of_object(state.centroid_ct_in_ns) < self.max_k
+                || state
+                    .centroid_pt_in_ne
+                    .is_some_and(|c| self.k_of_object(c) < self.max_k))
         {
-            if self.k_of_object(state.centroid_ct_in_ns) < self.graph.config.max_dim
-                || self.k_of_object(state.centroid_pt_in_ne) < self.graph.config.max_dim
-            {
-                self.min_heap_reserve.push(state);
-            }
+            self.min_heap_reserve.push(state);
         }
     }
 
-    pub fn map_new_edge(&self) -> HashMap<EdgeId, Vec<NodeId>> {
+    pub fn map_new_edge(&self) -> HashMap<EdgeId, Vec<GraphEdgeId>> {
         self.graph.map_new_edge()
     }
 
+    pub fn edge_len(&self, edge_id: GraphEdgeId) -> f32 {
+        self.graph.new_edge_len(edge_id)
+    }
+
     pub fn set_initialized(&mut self) {
         self.is_initial = false;
     }
@@ -208,16 +216,7 @@ impl<'a> VoronoiMinHeap<'a> {
         self.visited = HashSet::new();
     }
 
-    pub fn is_original_edge(&self, edge_id: EdgeId) -> bool {
-        if edge_id > 100000 {
-            false
-        } else {
-            true
-        }
-    }
-
     fn k_of_object(&self, object_id: ObjectId) -> K {
-        let object_id = Graph::as_object_id(object_id);
         *self.map_object_id_k.get(&object_id).unwrap()
     }
 
@@ -231,7 +230,7 @@ impl<'a> VoronoiMinHeap<'a> {
         }
     }
 
-    pub fn remove_cost(&mut self, node_id: NodeId) {
+    pub fn remove_cost(&mut self, node_id: GraphNodeId) {
         self.cost_map.remove(&node_id);
     }
 
@@ -268,16 +267,19 @@ impl<'a> Iterator for VoronoiMinHeap<'a> {
                 edge,
             } = state;
 
-            if let Some((centroid_id, cost)) = self.cost_map.get(&state.end_node_id) {
-                state.centroid_pt_in_ne = *centroid_id;
-                state.cost_pt_to_ne = *cost;
+            if let Some((centroid_id, cost)) = self.cost(state.end_node_id) {
+                state.centroid_pt_in_ne = Some(centroid_id);
+                state.cost_pt_to_ne = cost;
+                if centroid_id != centroid_ct_in_ns {
+                    state.smallest_k = self.smallest_k(centroid_ct_in_ns, centroid_id);
+                }
             }
 
             if cost_ct_to_ns > self.max_dist {
                 continue;
             }
 
-            if self.visit(start_node_id, end_node_id) {
+            if self.visit(edge.unwrap().id) {
                 continue;
             }
 
@@ -292,52 +294,54 @@ impl<'a> Iterator for VoronoiMinHeap<'a> {
 
             self.reserve_state(state);
 
-            for node_id in self.graph.neighbors(end_node_id) {
-                if self.is_visited(node_id, end_node_id) {
+            // node end is reached by a shorter path, which goes on from there
+            let is_owned = self
+                .cost(end_node_id)
+                .is_none_or(|(_, cost)| cost >= cost_ct_to_ne);
+            let edges = if is_owned {
+                self.graph.next_edges(end_node_id)
+            } else {
+                Vec::new()
+            };
+
+            for edge in edges {
+                if self.is_visited(edge.id) {
                     continue;
                 }
 
-                let edge = self.graph.edge(node_id, end_node_id);
-                let cost_next = {
-                    if let Some(edge) = edge {
-                        cost_ct_to_ne + edge.len
-                    } else {
-                        cost_ct_to_ne
-                    }
-                };
+                let node_id = edge.other(end_node_id);
+                let cost_next = cost_ct_to_ne + edge.len;
 
                 if !self.is_initial {
-                    if let Some(edge) = edge {
-                        if let Some((_centroid_id, k)) = self.map_centroid_edge_id.get(&edge.id) {
-                            if *k > self.current_k {
-                                continue;
-                            }
+                    if let Some((_centroid_id, k)) = self.map_centroid_edge_id.get(&edge.id) {
+                        if *k > self.current_k {
+                            continue;
                         }
                     }
+                }
+                if self.cost(node_id).is_none() {
                     self.cost_map.remove(&node_id);
                 }
 
                 let some_cost = self.cost_map.get_mut(&node_id);
                 if let Some(struct_cost) = some_cost {
-                    let (existing_centroid, prev_cost) = struct_cost.clone();
-                    if (existing_centroid == centroid_ct_in_ns && cost_next < prev_cost)
-                        || (existing_centroid != centroid_ct_in_ns)
-                    {
-                        if cost_next < prev_cost {
-                            *struct_cost = (centroid_ct_in_ns, cost_next);
-                        }
-                        self.min_heap.push(TraverseState {
-                            cost_ct_to_ns: cost_ct_to_ne,
-                            cost_ct_to_ne: cost_next,
-                            cost_pt_to_ne: prev_cost,
-                            centroid_ct_in_ns: centroid_ct_in_ns,
-                            centroid_pt_in_ne: existing_centroid,
-                            start_node_id: end_node_id,
-                            end_node_id: node_id,
-                            smallest_k: self.smallest_k(centroid_ct_in_ns, existing_centroid),
-                            edge: SimpleEdge::from_some(edge),
-                        });
+                    let (existing_centroid, prev_cost) = *struct_cost;
+                    if cost_next < prev_cost {
+                        *struct_cost = (centroid_ct_in_ns, cost_next);
                     }
+                    // pushed even if `node_id` is closer by another path of the
+                    // same centroid, so the edge is still covered from this side
+                    self.min_heap.push(TraverseState {
+                        cost_ct_to_ns: cost_ct_to_ne,
+                        cost_ct_to_ne: cost_next,
+                        cost_pt_to_ne: prev_cost,
+                        centroid_ct_in_ns,
+                        centroid_pt_in_ne: Some(existing_centroid),
+                        start_node_id: end_node_id,
+                        end_node_id: node_id,
+                        smallest_k: self.smallest_k(centroid_ct_in_ns, existing_centroid),
+                        edge: SimpleEdge::from_some(Some(edge)),
+                    });
                 } else {
                     self.cost_map
                         .insert(node_id, (centroid_ct_in_ns, cost_next));
@@ -346,12 +350,12 @@ impl<'a> Iterator for VoronoiMinHeap<'a> {
                         cost_ct_to_ns: cost_ct_to_ne,
                         cost_ct_to_ne: cost_next,
                         cost_pt_to_ne: 0.0,
-                        centroid_ct_in_ns: centroid_ct_in_ns,
-                        centroid_pt_in_ne: 0,
+                        centroid_ct_in_ns,
+                        centroid_pt_in_ne: None,
                         start_node_id: end_node_id,
                         end_node_id: node_id,
                         smallest_k: (0, Position::End),
-                        edge: SimpleEdge::from_some(edge),
+                        edge: SimpleEdge::from_some(Some(edge)),
                     });
                 }
             }
@@ -373,9 +377,9 @@ pub struct TraverseState {
     pub cost_ct_to_ne: f32,            // cost of current traverse to node end
     pub cost_pt_to_ne: f32,            // cost of previous traverse to node end
     pub centroid_ct_in_ns: CentroidId, // centroid of current traverse in node start
-    pub centroid_pt_in_ne: CentroidId, // centroid of previous traverse in node end
-    pub start_node_id: NodeId,         // node start
-    pub end_node_id: NodeId,           // node end
+    pub centroid_pt_in_ne: Option<CentroidId>, // centroid of previous traverse in node end
+    pub start_node_id: GraphNodeId,    // node start
+    pub end_node_id: GraphNodeId,      // node end
     pub smallest_k: (K, Position),
     pub edge: Option<SimpleEdge>,
 }
@@ -386,10 +390,11 @@ impl Ord for TraverseState {
             panic!("TraverseState.cost shouldn't be a NaN!");
         }
 
+        // reversed, so `BinaryHeap` pops the smallest cost first
         if self.cost_ct_to_ne < other.cost_ct_to_ne {
-            Ordering::Less
-        } else if self.cost_ct_to_ne > other.cost_ct_to_ne {
             Ordering::Greater
+        } else if self.cost_ct_to_ne > other.cost_ct_to_ne {
+            Ordering::Less
         } else {
             Ordering::Equal
         }
@@ -398,7 +403,7 @@ impl Ord for TraverseState {
 
 impl PartialOrd for TraverseState {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        other.cost_ct_to_ne.partial_cmp(&self.cost_ct_to_ne)
+        Some(self.cmp(other))
     }
 }
 
@@ -415,24 +420,22 @@ impl Eq for TraverseState {}
 
 #[derive(Copy, Clone, Debug)]
 pub struct SimpleEdge {
-    pub id: EdgeId,
-    pub ni: NodeId,
-    pub nj: NodeId,
+    pub id: GraphEdgeId,
+    pub ni: GraphNodeId,
+    pub nj: GraphNodeId,
     pub len: f32,
+    pub oneway: bool,
 }
 
 impl SimpleEdge {
     pub fn from_some(edge: Option<&Edge>) -> Option<Self> {
-        if let Some(e) = edge {
-            Some(SimpleEdge {
-                id: e.id,
-                ni: e.ni,
-                nj: e.nj,
-                len: e.len,
-            })
-        } else {
-            None
-        }
+        edge.map(|e| SimpleEdge {
+            id: e.id,
+            ni: e.ni,
+            nj: e.nj,
+            len: e.len,
+            oneway: e.oneway,
+        })
     }
 }
 
@@ -444,19 +447,33 @@ mod tests {
     #[test]
     fn new_voronoi_minheap() {
         let conf = Arc::new(AppConfig::default());
-        let mut graph = Graph::new(conf);
+        let graph = Graph::new(conf).unwrap();
         let mut map_object_id_k = HashMap::new();
         map_object_id_k.insert(1, 3);
         map_object_id_k.insert(2, 4);
         map_object_id_k.insert(3, 3);
-        let voronoi_minheap = VoronoiMinHeap::new(&mut graph, vec![1, 2, 3], map_object_id_k, 3);
+        let mut view = GraphView::new(&graph);
+        view.split_object_ids(&[1, 2, 3]);
+        let voronoi_minheap = VoronoiMinHeap::new(view, vec![1, 2, 3], map_object_id_k, 3, 4);
 
-        let mut count = 0;
-        for state in voronoi_minheap {
-            count += 1;
-            println!("{:#?}", state);
-        }
+        let popped: Vec<(GraphEdgeId, CentroidId)> = voronoi_minheap
+            .map(|state| (state.edge.unwrap().id, state.centroid_ct_in_ns))
+            .collect();
 
-        assert_eq!(count, 5);
+        // Edges 3, 4 and 5 hold objects 3, 1 and 2. They are split into the
+        // edges before and after each object and are no longer traversed as
+        // a whole, which only crossed the pieces again.
+        use GraphEdgeId::*;
+        let expected = vec![
+            (BeforeObject(1), 1),
+            (AfterObject(2), 2),
+            (AfterObject(1), 1),
+            (AfterObject(3), 3),
+            (BeforeObject(2), 2),
+            (BeforeObject(3), 3),
+            (Original(1), 3),
+            (Original(2), 3),
+        ];
+        assert_eq!(popped, expected);
     }
 }
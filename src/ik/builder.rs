@@ -0,0 +1,167 @@
+use crate::queries::Group;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::prelude::*;
+
+/// Compute k-dominant zones of all objects of a graph. Zones of an object do
+/// not depend on zones of other objects, so objects are spread over a pool of
+/// threads, each filling its own partial result, merged at the end. Each
+/// object is compared with its neighbors once for all groups of queries, see
+/// `SubspaceLattice`.
+pub struct ZoneBuilder<'a> {
+    graph: &'a Graph,
+    queries: &'a Queries,
+    /// Pool of `threads`, the global pool if none
+    pool: Option<ThreadPool>,
+}
+
+impl<'a> ZoneBuilder<'a> {
+    pub fn new(graph: &'a Graph, queries: &'a Queries) -> Self {
+        ZoneBuilder {
+            graph,
+            queries,
+            pool: None,
+        }
+    }
+
+    /// Number of threads, `0` uses one thread per CPU. The pool is started
+    /// here and kept for every build, zones are built on the global pool
+    /// unless this is called.
+    pub fn threads(mut self, threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        self.pool = Some(ThreadPoolBuilder::new().num_threads(threads).build()?);
+        Ok(self)
+    }
+
+    pub fn build(&self) -> ResultVoronoi {
+        let mut objects = self.graph.all_objects();
+        objects.sort_by_key(|o| o.id);
+
+        let lattice = SubspaceLattice::new(self.queries);
+        let build = || {
+            objects
+                .par_iter()
+                .fold(
+                    || self.empty_result(),
+                    |mut result, object| {
+                        let comparisons = SubspaceLattice::compare(self.graph, object.clone());
+                        let dom_traverses = lattice.dom_traverses(&comparisons);
+                        for (i, (g, dom_traverse)) in
+                            self.queries.iter().zip(&dom_traverses).enumerate()
+                        {
+                            let max_k = lattice.max_k(i);
+                            Self::object_zones(self.graph, &mut result, g, dom_traverse, max_k);
+                        }
+                        result
+                    },
+                )
+                .reduce(
+                    || self.empty_result(),
+                    |mut result, partial| {
+                        result.merge(partial);
+                        result
+                    },
+                )
+        };
+
+        match &self.pool {
+            Some(pool) => pool.install(build),
+            None => build(),
+        }
+    }
+
+    fn empty_result(&self) -> ResultVoronoi {
+        ResultVoronoi::from_edge_ids(self.graph.map_edges())
+    }
+
+    /// Save zones of the originator of `dom_traverse` for every k of the
+    /// queries of `group`, from the smallest k to the greatest.
+    fn object_zones(
+        graph: &Graph,
+        result: &mut ResultVoronoi,
+        group: &Group,
+        dom_traverse: &DomTraverse,
+        max_k: K,
+    ) {
+        let mut g = group.clone();
+        if let Some(q) = g.pop_first() {
+            let mut voronoi = Voronoi::from_dom_traverse(graph, dom_traverse, q.k, max_k);
+            voronoi.save_to_result(result, &g.zone_key(q.k));
+
+            let mut k = q.k;
+            for q in g.iter() {
+                // queries of the same k share their zones
+                if q.k == k {
+                    continue;
+                }
+                k = q.k;
+                voronoi.continue_voronoi(q.k);
+                voronoi.save_to_result(result, &g.zone_key(q.k));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ik::fixture;
+    use std::sync::Arc;
+
+    #[test]
+    fn parallel_matches_serial() {
+        let (graph, queries) = fixture::graph_and_queries();
+
+        let serial = ZoneBuilder::new(&graph, &queries)
+            .threads(1)
+            .unwrap()
+            .build();
+        let parallel = ZoneBuilder::new(&graph, &queries)
+            .threads(4)
+            .unwrap()
+            .build();
+
+        let locations = fixture::edge_locations(graph.map_edges().values());
+        for g in queries.iter() {
+            for q in g.iter() {
+                let key = g.zone_key(q.k);
+                assert_eq!(
+                    parallel.locate_all(&locations, &key),
+                    serial.locate_all(&locations, &key)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn queries_of_the_same_k() {
+        let (graph, queries) = fixture::graph_and_queries();
+        let (_, mut duplicated) = fixture::graph_and_queries();
+        for (id, k) in [(10, 3), (11, 4), (12, 4)] {
+            duplicated.insert(Arc::new(Query {
+                id,
+                k,
+                dimensions: vec![1, 2, 3, 4],
+                preferences: vec![Preference::Max; 4],
+            }));
+        }
+
+        let expected = ZoneBuilder::new(&graph, &queries).build();
+        let result = ZoneBuilder::new(&graph, &duplicated).build();
+        assert_eq!(result.keys(), expected.keys());
+        let locations = fixture::edge_locations(graph.map_edges().values());
+        for key in result.keys() {
+            assert_eq!(
+                result.locate_all(&locations, &key),
+                expected.locate_all(&locations, &key)
+            );
+            // ranges are saved once
+            for object_id in result.object_ids(&key) {
+                assert_eq!(
+                    result.zone(object_id, &key).len(),
+                    expected.zone(object_id, &key).len()
+                );
+            }
+        }
+    }
+}
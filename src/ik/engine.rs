@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Keep k-dominant zones of all registered queries up to date while
+/// objects are inserted into and deleted from the graph.
+pub struct UpdateEngine {
+    graph: Graph,
+    queries: Queries,
+    lattice: SubspaceLattice,
+    result: ResultVoronoi,
+}
+
+impl UpdateEngine {
+    /// Create new engine, computing zones of objects already in `graph`.
+    pub fn new(graph: Graph, queries: Queries) -> Self {
+        let result = ZoneBuilder::new(&graph, &queries).build();
+        let lattice = SubspaceLattice::new(&queries);
+        UpdateEngine {
+            graph,
+            queries,
+            lattice,
+            result,
+        }
+    }
+
+    /// Apply one event according to its `action`.
+    pub fn apply(&mut self, object: Arc<DataObject>) -> Result<(), UpdateError> {
+        match object.action {
+            Action::Insertion => self.insert(object),
+            Action::Deletion => self.delete(object.id),
+        }
+    }
+
+    /// Apply events in order, stopping at the first failing one.
+    pub fn apply_all<I>(&mut self, events: I) -> Result<(), UpdateError>
+    where
+        I: IntoIterator<Item = Arc<DataObject>>,
+    {
+        for object in events {
+            self.apply(object)?;
+        }
+        Ok(())
+    }
+
+    /// Insert new object. Its zones are computed and zones of objects it
+    /// dominates are repaired.
+    pub fn insert(&mut self, object: Arc<DataObject>) -> Result<(), UpdateError> {
+        if self.graph.has_object(object.id) {
+            return Err(UpdateError::DuplicateObject(object.id));
+        }
+        if !self.graph.has_edge(object.edge_id) {
+            return Err(UpdateError::UnknownEdge {
+                object_id: object.id,
+                edge_id: object.edge_id,
+            });
+        }
+
+        self.graph.insert_object(object.clone());
+        let comparisons = SubspaceLattice::compare(&self.graph, object.clone());
+        let dom_traverses = self.lattice.dom_traverses(&comparisons);
+        let max_ks = vec![K::MAX; dom_traverses.len()];
+        self.compute_zones(&dom_traverses, &max_ks);
+
+        // `object` is a new centroid in their zones for every k up to `k`
+        self.repair_dominated(&dom_traverses);
+        Ok(())
+    }
+
+    /// Delete object. Its zones are removed and zones of objects it
+    /// dominated are repaired.
+    pub fn delete(&mut self, object_id: ObjectId) -> Result<(), UpdateError> {
+        if !self.graph.has_object(object_id) {
+            return Err(UpdateError::UnknownObject(object_id));
+        }
+
+        let object = self.graph.object(object_id);
+        let comparisons = SubspaceLattice::compare(&self.graph, object);
+        let dom_traverses = self.lattice.dom_traverses(&comparisons);
+
+        self.graph.remove_object(object_id);
+        self.result.remove_all(object_id);
+        self.repair_dominated(&dom_traverses);
+        Ok(())
+    }
+
+    /// Register `query` and compute its zones, unless a query of the same
+    /// subspace and k already has them. Its dimensions are sorted, so a
+    /// subspace matches whatever order it was listed in.
+    pub fn add_query(&mut self, mut query: Arc<Query>) -> Result<(), UpdateError> {
+        if self.queries.query(query.id).is_some() {
+            return Err(UpdateError::DuplicateQuery(query.id));
+        }
+        Self::check_query(&query, self.graph.config.max_dim)?;
+        if !query.dimensions.is_sorted() {
+            let mut criteria: Vec<_> = query
+                .dimensions
+                .iter()
+                .copied()
+                .zip(query.preferences.iter().copied())
+                .collect();
+            criteria.sort_by_key(|&(d, _)| d);
+            let (dimensions, preferences) = criteria.into_iter().unzip();
+            query = Arc::new(Query {
+                dimensions,
+                preferences,
+                ..query.as_ref().clone()
+            });
+        }
+
+        let is_computed = self.result.has_key(&ZoneKey::from(query.as_ref()));
+        self.queries.insert(query.clone());
+        self.lattice = SubspaceLattice::new(&self.queries);
+        if !is_computed {
+            let queries = Queries::new(vec![query]);
+            self.result
+                .merge(ZoneBuilder::new(&self.graph, &queries).build());
+        }
+        Ok(())
+    }
+
+    /// Unregister query `query_id`, dropping its zones unless another
+    /// query of the same subspace and k still needs them.
+    pub fn remove_query(&mut self, query_id: QueryId) -> Result<Arc<Query>, UpdateError> {
+        let query = self
+            .queries
+            .remove(query_id)
+            .ok_or(UpdateError::UnknownQuery(query_id))?;
+        self.lattice = SubspaceLattice::new(&self.queries);
+
+        let key = ZoneKey::from(query.as_ref());
+        let is_needed = self
+            .queries
+            .iter()
+            .flat_map(|g| g.iter())
+            .any(|q| ZoneKey::from(q.as_ref()) == key);
+        if !is_needed {
+            self.result.remove_key(&key);
+        }
+        Ok(query)
+    }
+
+    /// Check that `query` has a preference for each of its dimensions, each
+    /// dimension once and up to `max_dim`, and k up to the number of its
+    /// dimensions.
+    pub(crate) fn check_query(query: &Query, max_dim: DimensionIndex) -> Result<(), UpdateError> {
+        let is_valid = query.k >= 1
+            && query.k as usize <= query.dimensions.len()
+            && query.preferences.len() == query.dimensions.len()
+            && query.dimensions.iter().all(|d| (1..=max_dim).contains(d));
+        if !is_valid {
+            return Err(UpdateError::InvalidQuery(query.id));
+        }
+
+        let mut dimensions = query.dimensions.clone();
+        dimensions.sort_unstable();
+        if let Some(w) = dimensions.windows(2).find(|w| w[0] == w[1]) {
+            return Err(UpdateError::DuplicateDimension {
+                query_id: query.id,
+                dimension: w[0],
+            });
+        }
+        Ok(())
+    }
+
+    /// Recompute zones of objects dominated by the originator of
+    /// `dom_traverses`, one per group, comparing each object once for all
+    /// groups.
+    fn repair_dominated(&mut self, dom_traverses: &[DomTraverse]) {
+        // greatest k to recompute of each group, none if not dominated
+        let mut dominate_objects: HashMap<ObjectId, Vec<K>> = HashMap::new();
+        for (i, dom_traverse) in dom_traverses.iter().enumerate() {
+            for (object_id, k) in dom_traverse.map_dominate_objects() {
+                dominate_objects
+                    .entry(object_id)
+                    .or_insert_with(|| vec![0; dom_traverses.len()])[i] = k;
+            }
+        }
+
+        let mut object_ids: Vec<ObjectId> = dominate_objects.keys().copied().collect();
+        object_ids.sort_unstable();
+        for object_id in object_ids {
+            let comparisons = SubspaceLattice::compare(&self.graph, self.graph.object(object_id));
+            let dom_traverses = self.lattice.dom_traverses(&comparisons);
+            self.compute_zones(&dom_traverses, &dominate_objects[&object_id]);
+        }
+    }
+
+    /// (Re)compute zones of the originator of `dom_traverses` for queries
+    /// of each group with k not greater than the max k of the group.
+    fn compute_zones(&mut self, dom_traverses: &[DomTraverse], max_ks: &[K]) {
+        let groups = self.queries.iter().zip(dom_traverses).zip(max_ks);
+        for (i, ((group, dom_traverse), max_k)) in groups.enumerate() {
+            let mut g = group.clone();
+            g.remove_greater_k(*max_k);
+            if let Some(q) = g.pop_first() {
+                let object_id = dom_traverse.originator().id;
+                let key = g.zone_key(q.k);
+                self.result.remove(object_id, &key);
+                let mut voronoi = Voronoi::from_dom_traverse(
+                    &self.graph,
+                    dom_traverse,
+                    q.k,
+                    self.lattice.max_k(i),
+                );
+                voronoi.save_to_result(&mut self.result, &key);
+
+                for q in g.iter() {
+                    let key = g.zone_key(q.k);
+                    self.result.remove(object_id, &key);
+                    voronoi.continue_voronoi(q.k);
+                    voronoi.save_to_result(&mut self.result, &key);
+                }
+            }
+        }
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    pub fn queries(&self) -> &Queries {
+        &self.queries
+    }
+
+    pub fn result(&self) -> &ResultVoronoi {
+        &self.result
+    }
+
+    pub fn into_result(self) -> ResultVoronoi {
+        self.result
+    }
+}
+
+/// Event which cannot be applied to the graph
+#[derive(Debug)]
+pub enum UpdateError {
+    /// Inserted object id is already in the graph
+    DuplicateObject(ObjectId),
+    /// Deleted object id is not in the graph
+    UnknownObject(ObjectId),
+    /// Inserted object lies on an edge which is not in the graph
+    UnknownEdge {
+        object_id: ObjectId,
+        edge_id: EdgeId,
+    },
+    /// Added query id is already registered
+    DuplicateQuery(QueryId),
+    /// Removed query id is not registered
+    UnknownQuery(QueryId),
+    /// Added query has no dimensions, a dimension out of `max_dim`, or k
+    /// out of its dimensions
+    InvalidQuery(QueryId),
+    /// Added query lists a dimension more than once
+    DuplicateDimension {
+        query_id: QueryId,
+        dimension: DimensionIndex,
+    },
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::DuplicateObject(id) => write!(f, "object {} already exists", id),
+            UpdateError::UnknownObject(id) => write!(f, "object {} does not exist", id),
+            UpdateError::UnknownEdge { object_id, edge_id } => {
+                write!(f, "object {} lies on unknown edge {}", object_id, edge_id)
+            }
+            UpdateError::DuplicateQuery(id) => write!(f, "query {} already exists", id),
+            UpdateError::UnknownQuery(id) => write!(f, "query {} does not exist", id),
+            UpdateError::InvalidQuery(id) => write!(f, "query {} is invalid", id),
+            UpdateError::DuplicateDimension {
+                query_id,
+                dimension,
+            } => write!(f, "query {} lists dimension {} twice", query_id, dimension),
+        }
+    }
+}
+
+impl Error for UpdateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ik::fixture;
+    use std::collections::BTreeSet;
+
+    fn locate_everywhere(engine: &UpdateEngine) -> Vec<Vec<ObjectId>> {
+        let locations = fixture::edge_locations(engine.graph().map_edges().values());
+
+        let keys: BTreeSet<ZoneKey> = engine
+            .queries()
+            .iter()
+            .flat_map(|g| g.iter().map(move |q| g.zone_key(q.k)))
+            .collect();
+        let mut found = Vec::new();
+        for key in keys {
+            found.append(&mut engine.result().locate_all(&locations, &key));
+        }
+        found
+    }
+
+    #[test]
+    fn insertion_matches_construction() {
+        let conf = Arc::new(AppConfig::default());
+        let reader = Reader::new(conf.clone());
+        let objects = reader.read_object_csv().unwrap();
+
+        let (graph, queries) = fixture::graph_and_queries();
+        let full = UpdateEngine::new(graph, queries);
+
+        let queries = Queries::new(reader.read_query_csv().unwrap());
+        let graph = Graph::new_empty_object(conf).unwrap();
+        let mut engine = UpdateEngine::new(graph, queries);
+        engine.apply_all(objects).unwrap();
+
+        assert_eq!(locate_everywhere(&engine), locate_everywhere(&full));
+    }
+
+    #[test]
+    fn deletion_matches_construction() {
+        let conf = Arc::new(AppConfig::default());
+        let reader = Reader::new(conf.clone());
+        let objects = reader.read_object_csv().unwrap();
+
+        let (graph, queries) = fixture::graph_and_queries();
+        let mut engine = UpdateEngine::new(graph, queries);
+        engine.delete(1).unwrap();
+
+        let queries = Queries::new(reader.read_query_csv().unwrap());
+        let graph = Graph::new_empty_object(conf).unwrap();
+        let mut expected = UpdateEngine::new(graph, queries);
+        expected
+            .apply_all(objects.into_iter().filter(|o| o.id != 1))
+            .unwrap();
+
+        assert_eq!(locate_everywhere(&engine), locate_everywhere(&expected));
+        assert!(engine.delete(1).is_err());
+    }
+
+    fn subspace_query(id: QueryId, k: K) -> Arc<Query> {
+        Arc::new(Query {
+            id,
+            k,
+            dimensions: vec![1, 3],
+            preferences: vec![Preference::Max; 2],
+        })
+    }
+
+    #[test]
+    fn add_query_matches_construction() {
+        let (graph, queries) = fixture::graph_and_queries();
+        let mut engine = UpdateEngine::new(graph, queries);
+        engine.add_query(subspace_query(10, 2)).unwrap();
+        engine.add_query(subspace_query(11, 1)).unwrap();
+        // zones of an already registered subspace and k
+        engine.add_query(subspace_query(12, 2)).unwrap();
+
+        let (graph, mut queries) = fixture::graph_and_queries();
+        queries.insert(subspace_query(10, 2));
+        queries.insert(subspace_query(11, 1));
+        let expected = UpdateEngine::new(graph, queries);
+        assert_eq!(locate_everywhere(&engine), locate_everywhere(&expected));
+
+        assert!(matches!(
+            engine.add_query(subspace_query(10, 2)),
+            Err(UpdateError::DuplicateQuery(10))
+        ));
+        assert!(matches!(
+            engine.add_query(subspace_query(13, 3)),
+            Err(UpdateError::InvalidQuery(13))
+        ));
+    }
+
+    #[test]
+    fn remove_query_matches_construction() {
+        let (graph, mut all) = fixture::graph_and_queries();
+        all.insert(subspace_query(10, 3));
+        all.insert(subspace_query(11, 2));
+        all.insert(subspace_query(12, 2));
+        let mut engine = UpdateEngine::new(graph, all);
+        for query_id in [10, 11, 12] {
+            assert_eq!(engine.remove_query(query_id).unwrap().id, query_id);
+        }
+        assert!(matches!(
+            engine.remove_query(10),
+            Err(UpdateError::UnknownQuery(10))
+        ));
+
+        let (graph, queries) = fixture::graph_and_queries();
+        let expected = UpdateEngine::new(graph, queries);
+        assert_eq!(locate_everywhere(&engine), locate_everywhere(&expected));
+        assert_eq!(engine.result().keys(), expected.result().keys());
+    }
+
+    fn query_of(
+        id: QueryId,
+        dimensions: Vec<DimensionIndex>,
+        preferences: Vec<Preference>,
+    ) -> Arc<Query> {
+        Arc::new(Query {
+            id,
+            k: 2,
+            dimensions,
+            preferences,
+        })
+    }
+
+    #[test]
+    fn query_dimensions_in_any_order() {
+        use Preference::*;
+        let (graph, queries) = fixture::graph_and_queries();
+        let mut engine = UpdateEngine::new(graph, queries);
+        engine
+            .add_query(query_of(10, vec![3, 1], vec![Min, Max]))
+            .unwrap();
+        let added = engine.queries().query(10).unwrap();
+        assert_eq!(added.dimensions, vec![1, 3]);
+        assert_eq!(added.preferences, vec![Max, Min]);
+        engine
+            .add_query(query_of(11, vec![1, 3], vec![Max, Min]))
+            .unwrap();
+
+        let (graph, mut queries) = fixture::graph_and_queries();
+        queries.insert(query_of(11, vec![1, 3], vec![Max, Min]));
+        let expected = UpdateEngine::new(graph, queries);
+        assert_eq!(locate_everywhere(&engine), locate_everywhere(&expected));
+
+        // the query listed as [1, 3] still needs zones of the one listed as [3, 1]
+        engine.remove_query(10).unwrap();
+        assert_eq!(locate_everywhere(&engine), locate_everywhere(&expected));
+        assert_eq!(engine.result().keys(), expected.result().keys());
+
+        engine.remove_query(11).unwrap();
+        let (graph, queries) = fixture::graph_and_queries();
+        let expected = UpdateEngine::new(graph, queries);
+        assert_eq!(engine.result().keys(), expected.result().keys());
+
+        assert!(matches!(
+            engine.add_query(Arc::new(Query {
+                id: 11,
+                k: 1,
+                dimensions: vec![2, 1, 2],
+                preferences: vec![Preference::Max; 3],
+            })),
+            Err(UpdateError::DuplicateDimension {
+                query_id: 11,
+                dimension: 2
+            })
+        ));
+    }
+}
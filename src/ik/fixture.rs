@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Graph and queries of the default dataset.
+pub(crate) fn graph_and_queries() -> (Graph, Queries) {
+    let conf = Arc::new(AppConfig::default());
+    let reader = Reader::new(conf.clone());
+    let graph = Graph::new(conf).unwrap();
+    let queries = Queries::new(reader.read_query_csv().unwrap());
+    (graph, queries)
+}
+
+/// Every twentieth of `edges`, from node i to node j, by edge id.
+pub(crate) fn edge_locations<'a, E>(edges: E) -> Vec<Location>
+where
+    E: IntoIterator<Item = &'a Arc<DataEdge>>,
+{
+    let mut edges: Vec<&Arc<DataEdge>> = edges.into_iter().collect();
+    edges.sort_by_key(|e| e.id);
+    edges
+        .into_iter()
+        .flat_map(|e| (0..=20).map(move |i| Location::Edge(e.id, e.len * i as f32 / 20.0)))
+        .collect()
+}
@@ -0,0 +1,243 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Plan to derive dominance of an object in every registered subspace from
+/// one traversal and one comparison per pair of objects.
+///
+/// Subspaces, one per group of `Queries`, form a lattice by inclusion.
+/// Scores of a subspace are the scores of the largest registered subspace
+/// it contains, its parent, plus the scores of the dimensions it adds, so
+/// `[1, 2, 3, 4]` only counts dimension 4 on top of `[1, 2, 3]`.
+#[derive(Debug)]
+pub struct SubspaceLattice {
+    /// Subspaces in the order of `Queries::iter`
+    subspaces: Vec<Subspace>,
+    /// Indexes of `subspaces`, each after its parent
+    order: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Subspace {
+    len: K,
+    parent: Option<usize>,
+    /// Dimensions of this subspace not in its parent, 0-based, with the
+    /// preference of each
+    added: Vec<(usize, Preference)>,
+}
+
+/// Dimension-wise comparison of an object with the objects around it.
+#[derive(Debug)]
+pub struct Comparisons {
+    originator: Arc<DataObject>,
+    /// How each object compares to originator, by attribute
+    others: Vec<(ObjectId, Vec<Ordering>)>,
+}
+
+impl SubspaceLattice {
+    pub fn new(queries: &Queries) -> Self {
+        let pairs: Vec<Vec<(DimensionIndex, Preference)>> = queries
+            .iter()
+            .map(|g| {
+                let mut pairs: Vec<_> = g
+                    .dimensions()
+                    .iter()
+                    .copied()
+                    .zip(g.preferences().iter().copied())
+                    .collect();
+                pairs.sort_unstable();
+                pairs
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..pairs.len()).collect();
+        order.sort_by_key(|i| pairs[*i].len());
+
+        let mut subspaces = Vec::with_capacity(pairs.len());
+        for (i, subspace) in pairs.iter().enumerate() {
+            // a parent agrees on the preference of every dimension it has
+            let parent = (0..pairs.len())
+                .filter(|j| *j != i && pairs[*j].len() < subspace.len())
+                .filter(|j| Self::difference(subspace, &pairs[*j]).is_some())
+                .max_by_key(|j| (pairs[*j].len(), std::cmp::Reverse(*j)));
+            let added = match parent {
+                Some(j) => Self::difference(subspace, &pairs[j]).unwrap(),
+                None => subspace.clone(),
+            };
+            subspaces.push(Subspace {
+                len: subspace.len() as K,
+                parent,
+                added: added
+                    .into_iter()
+                    .map(|(d, preference)| ((d - 1) as usize, preference))
+                    .collect(),
+            });
+        }
+
+        SubspaceLattice { subspaces, order }
+    }
+
+    /// Dimensions of sorted `a` not in sorted `b`, if all of `b` are in `a`.
+    fn difference(
+        a: &[(DimensionIndex, Preference)],
+        b: &[(DimensionIndex, Preference)],
+    ) -> Option<Vec<(DimensionIndex, Preference)>> {
+        let mut difference = Vec::new();
+        let mut b = b.iter().peekable();
+        for pair in a {
+            if b.peek() == Some(&pair) {
+                b.next();
+            } else {
+                difference.push(*pair);
+            }
+        }
+        b.peek().is_none().then_some(difference)
+    }
+
+    /// Position in `Queries::iter` of the subspace the scores of the
+    /// `group`-th subspace are derived from.
+    pub fn parent(&self, group: usize) -> Option<usize> {
+        self.subspaces[group].parent
+    }
+
+    /// Compare `originator` with the objects around it, once for all
+    /// subspaces.
+    pub fn compare(graph: &Graph, originator: Arc<DataObject>) -> Comparisons {
+        let others = DomTraverse::neighborhood(graph, &originator)
+            .into_iter()
+            .map(|object| {
+                let orderings = originator
+                    .attr
+                    .iter()
+                    .zip(&object.attr)
+                    .map(|(src, dst)| src.partial_cmp(dst).unwrap_or(Ordering::Equal))
+                    .collect();
+                (object.id, orderings)
+            })
+            .collect();
+        Comparisons { originator, others }
+    }
+
+    /// Dominance of the originator of `comparisons`, in the order of
+    /// `Queries::iter`.
+    pub fn dom_traverses(&self, comparisons: &Comparisons) -> Vec<DomTraverse> {
+        let mut dom_traverses: Vec<DomTraverse> = self
+            .subspaces
+            .iter()
+            .map(|_| DomTraverse::new(comparisons.originator.clone()))
+            .collect();
+
+        let mut scores = vec![(0, 0); self.subspaces.len()];
+        for (object_id, orderings) in &comparisons.others {
+            for i in &self.order {
+                let subspace = &self.subspaces[*i];
+                let (mut src_score, mut dst_score) =
+                    subspace.parent.map_or((0, 0), |parent| scores[parent]);
+                for (d, preference) in &subspace.added {
+                    let ordering = match preference {
+                        Preference::Max => orderings[*d],
+                        Preference::Min => orderings[*d].reverse(),
+                    };
+                    match ordering {
+                        Ordering::Greater => src_score += 1,
+                        Ordering::Less => dst_score += 1,
+                        Ordering::Equal => {
+                            src_score += 1;
+                            dst_score += 1;
+                        }
+                    }
+                }
+                scores[*i] = (src_score, dst_score);
+            }
+
+            for (dom_traverse, (src_score, dst_score)) in dom_traverses.iter_mut().zip(&scores) {
+                dom_traverse.add(*object_id, *src_score, *dst_score);
+            }
+        }
+        dom_traverses
+    }
+
+    /// Number of dimensions of the `group`-th subspace, the greatest k its
+    /// dominance counts up to.
+    pub fn max_k(&self, group: usize) -> K {
+        self.subspaces[group].len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn sorted(map: &HashMap<K, Vec<ObjectId>>) -> Vec<(K, Vec<ObjectId>)> {
+        let mut sorted: Vec<_> = map
+            .iter()
+            .map(|(k, ids)| {
+                let mut ids = ids.clone();
+                ids.sort_unstable();
+                (*k, ids)
+            })
+            .collect();
+        sorted.sort_unstable();
+        sorted
+    }
+
+    #[test]
+    fn lattice_matches_dom_traverse() {
+        use Preference::*;
+        let conf = Arc::new(AppConfig::default());
+        let graph = Graph::new(conf).unwrap();
+        let query = |id, dimensions: Vec<DimensionIndex>, preferences| {
+            Arc::new(Query {
+                id,
+                k: 2,
+                dimensions,
+                preferences,
+            })
+        };
+        let queries = Queries::new(vec![
+            query(1, vec![1, 2, 3, 4], vec![Max; 4]),
+            query(2, vec![1, 2, 3], vec![Max; 3]),
+            query(3, vec![1, 3], vec![Max; 2]),
+            query(4, vec![1, 2], vec![Min, Max]),
+            query(5, vec![1, 2, 4], vec![Min, Max, Max]),
+            query(6, vec![3], vec![Max]),
+        ]);
+        let lattice = SubspaceLattice::new(&queries);
+        let groups: Vec<_> = queries.iter().collect();
+
+        let position = |dimensions: &[DimensionIndex], preferences: &[Preference]| {
+            groups
+                .iter()
+                .position(|g| g.dimensions() == dimensions && g.preferences() == preferences)
+        };
+        let full = position(&[1, 2, 3, 4], &[Max; 4]).unwrap();
+        assert_eq!(lattice.parent(full), position(&[1, 2, 3], &[Max; 3]));
+        let min = position(&[1, 2, 4], &[Min, Max, Max]).unwrap();
+        assert_eq!(lattice.parent(min), position(&[1, 2], &[Min, Max]));
+        assert_eq!(lattice.parent(position(&[3], &[Max]).unwrap()), None);
+
+        for object in graph.all_objects() {
+            let comparisons = SubspaceLattice::compare(&graph, object.clone());
+            let dom_traverses = lattice.dom_traverses(&comparisons);
+            for (i, g) in groups.iter().enumerate() {
+                let expected = DomTraverse::dominate_dominated_by(
+                    &graph,
+                    object.clone(),
+                    g.dimensions(),
+                    g.preferences(),
+                );
+                assert_eq!(lattice.max_k(i), g.dimensions().len() as K);
+                assert_eq!(
+                    sorted(&dom_traverses[i].dominate),
+                    sorted(&expected.dominate)
+                );
+                assert_eq!(
+                    sorted(&dom_traverses[i].dominated_by),
+                    sorted(&expected.dominated_by)
+                );
+            }
+        }
+    }
+}
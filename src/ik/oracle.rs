@@ -0,0 +1,246 @@
+use crate::queries::Group;
+use ordered_float::OrderedFloat as OF;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Slow reference for k-dominant zones, independent of `Voronoi`. A point
+/// belongs to the zone of object `o` for `k` when `o` is within `max_dist`
+/// and no object k-dominating `o` is closer, with distances from exact
+/// shortest paths between every pair of nodes, following one-way edges in
+/// the direction of the config.
+pub struct Oracle<'a> {
+    graph: &'a Graph,
+    objects: Vec<Arc<DataObject>>,
+    edges: HashMap<EdgeId, Arc<DataEdge>>,
+    node_dist: HashMap<NodeId, HashMap<NodeId, f32>>,
+}
+
+/// Location where `ResultVoronoi` disagrees with `Oracle`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    pub edge_id: EdgeId,
+    pub offset: f32,
+    pub k: K,
+    pub dimensions: Vec<DimensionIndex>,
+    /// Objects surely covering the location
+    pub expected: Vec<ObjectId>,
+    /// Objects covering the location, or within the tolerance of doing so
+    pub allowed: Vec<ObjectId>,
+    /// Objects found in `ResultVoronoi`
+    pub found: Vec<ObjectId>,
+}
+
+impl<'a> Oracle<'a> {
+    pub fn new(graph: &'a Graph) -> Self {
+        let edges = graph.map_edges();
+        let mut adjacency: HashMap<NodeId, Vec<(NodeId, f32)>> = HashMap::new();
+        for edge in edges.values() {
+            adjacency
+                .entry(edge.ni)
+                .or_default()
+                .push((edge.nj, edge.len));
+            let ni = adjacency.entry(edge.nj).or_default();
+            if !edge.oneway {
+                ni.push((edge.ni, edge.len));
+            }
+        }
+
+        let node_dist = adjacency
+            .keys()
+            .map(|n| (*n, Self::dijkstra(&adjacency, *n)))
+            .collect();
+
+        let mut objects = graph.all_objects();
+        objects.sort_by_key(|o| o.id);
+
+        Oracle {
+            graph,
+            objects,
+            edges,
+            node_dist,
+        }
+    }
+
+    fn dijkstra(
+        adjacency: &HashMap<NodeId, Vec<(NodeId, f32)>>,
+        start: NodeId,
+    ) -> HashMap<NodeId, f32> {
+        let mut dist = HashMap::new();
+        let mut min_heap = BinaryHeap::new();
+        min_heap.push(Reverse((OF(0.0), start)));
+        while let Some(Reverse((OF(cost), node_id))) = min_heap.pop() {
+            if dist.contains_key(&node_id) {
+                continue;
+            }
+            dist.insert(node_id, cost);
+            for (next, len) in &adjacency[&node_id] {
+                if !dist.contains_key(next) {
+                    min_heap.push(Reverse((OF(cost + len), *next)));
+                }
+            }
+        }
+        dist
+    }
+
+    fn node_dist(&self, a: NodeId, b: NodeId) -> f32 {
+        *self.node_dist[&a].get(&b).unwrap_or(&f32::INFINITY)
+    }
+
+    /// Nodes where a drive leaving the point at `offset` of `edge` first
+    /// arrives, with the distance to each.
+    fn exits(edge: &DataEdge, offset: f32) -> Vec<(NodeId, f32)> {
+        let mut exits = vec![(edge.nj, edge.len - offset)];
+        if !edge.oneway {
+            exits.push((edge.ni, offset));
+        }
+        exits
+    }
+
+    /// Nodes where a drive arriving at the point at `offset` of `edge` last
+    /// departs, with the distance from each.
+    fn entries(edge: &DataEdge, offset: f32) -> Vec<(NodeId, f32)> {
+        let mut entries = vec![(edge.ni, offset)];
+        if !edge.oneway {
+            entries.push((edge.nj, edge.len - offset));
+        }
+        entries
+    }
+
+    /// Shortest drive from the point at `offset_a` of `edge_a` to the point
+    /// at `offset_b` of `edge_b`.
+    fn drive(&self, edge_a: &DataEdge, offset_a: f32, edge_b: &DataEdge, offset_b: f32) -> f32 {
+        let mut dist = f32::INFINITY;
+        for (exit, cost_exit) in Self::exits(edge_a, offset_a) {
+            for (entry, cost_entry) in Self::entries(edge_b, offset_b) {
+                dist = dist.min(cost_exit + self.node_dist(exit, entry) + cost_entry);
+            }
+        }
+        if edge_a.id == edge_b.id && (!edge_a.oneway || offset_b >= offset_a) {
+            dist = dist.min((offset_b - offset_a).abs());
+        }
+        dist
+    }
+
+    /// Shortest distance between the point at `offset` of `edge_id` and
+    /// `object`, in the direction of the config.
+    pub fn distance(&self, edge_id: EdgeId, offset: f32, object: &DataObject) -> f32 {
+        let edge = &self.edges[&edge_id];
+        let object_edge = &self.edges[&object.edge_id];
+        let object_offset = object.dist * object_edge.len;
+        match self.graph.config.direction {
+            Direction::FromObject => self.drive(object_edge, object_offset, edge, offset),
+            Direction::ToObject => self.drive(edge, offset, object_edge, object_offset),
+        }
+    }
+
+    /// Whether `a` k-dominates `b` for at least `k`, over `dimensions`.
+    fn dominates(
+        a: &DataObject,
+        b: &DataObject,
+        k: K,
+        dimensions: &[DimensionIndex],
+        preferences: &[Preference],
+    ) -> bool {
+        debug_assert_eq!(dimensions.len(), preferences.len());
+        let mut a_score = 0;
+        let mut b_score = 0;
+        for (d, preference) in dimensions.iter().zip(preferences) {
+            let i = (*d - 1) as usize;
+            if !preference.is_better(b.attr[i], a.attr[i]) {
+                a_score += 1;
+            }
+            if !preference.is_better(a.attr[i], b.attr[i]) {
+                b_score += 1;
+            }
+        }
+        a_score > b_score && a_score >= k
+    }
+
+    /// Objects whose zone covers the point at `offset` of `edge_id`, as
+    /// `(sure, allowed)`. Objects within `tolerance` of a zone boundary are
+    /// only allowed.
+    pub fn objects_at(
+        &self,
+        edge_id: EdgeId,
+        offset: f32,
+        k: K,
+        dimensions: &[DimensionIndex],
+        preferences: &[Preference],
+        tolerance: f32,
+    ) -> (Vec<ObjectId>, Vec<ObjectId>) {
+        let max_dist = self.graph.config.max_dist;
+        let dist: Vec<f32> = self
+            .objects
+            .iter()
+            .map(|o| self.distance(edge_id, offset, o))
+            .collect();
+
+        let mut sure = Vec::new();
+        let mut allowed = Vec::new();
+        for (i, o) in self.objects.iter().enumerate() {
+            let mut nearest = max_dist;
+            for (j, c) in self.objects.iter().enumerate() {
+                if Self::dominates(c, o, k, dimensions, preferences) {
+                    nearest = nearest.min(dist[j]);
+                }
+            }
+
+            if dist[i] <= nearest + tolerance {
+                allowed.push(o.id);
+                if dist[i] < nearest - tolerance {
+                    sure.push(o.id);
+                }
+            }
+        }
+        (sure, allowed)
+    }
+
+    /// Compare zones of every query of `group` in `result` with the oracle
+    /// at `samples` + 1 evenly spaced points of each edge.
+    pub fn compare(
+        &self,
+        result: &ResultVoronoi,
+        group: &Group,
+        samples: usize,
+        tolerance: f32,
+    ) -> Vec<Mismatch> {
+        let mut edge_ids: Vec<EdgeId> = self.edges.keys().copied().collect();
+        edge_ids.sort_unstable();
+
+        let mut mismatches = Vec::new();
+        for q in group.iter() {
+            for edge_id in &edge_ids {
+                let len = self.edges[edge_id].len;
+                for i in 0..=samples {
+                    let offset = len * i as f32 / samples as f32;
+                    let (expected, allowed) = self.objects_at(
+                        *edge_id,
+                        offset,
+                        q.k,
+                        group.dimensions(),
+                        group.preferences(),
+                        tolerance,
+                    );
+                    let found = result.objects_at(*edge_id, offset, &group.zone_key(q.k));
+                    let is_valid = expected.iter().all(|o| found.contains(o))
+                        && found.iter().all(|o| allowed.contains(o));
+                    if !is_valid {
+                        mismatches.push(Mismatch {
+                            edge_id: *edge_id,
+                            offset,
+                            k: q.k,
+                            dimensions: group.dimensions().to_vec(),
+                            expected,
+                            allowed,
+                            found,
+                        });
+                    }
+                }
+            }
+        }
+        mismatches
+    }
+}
@@ -1,26 +1,45 @@
 use crate::prelude::*;
-use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 #[derive(Clone, Debug)]
 pub struct Edge {
-    pub id: EdgeId,
+    pub id: GraphEdgeId,
     pub len: f32,
-    pub ni: NodeId,
-    pub nj: NodeId,
+    pub ni: GraphNodeId,
+    pub nj: GraphNodeId,
+    /// Traversable from `ni` to `nj` only
+    pub oneway: bool,
     pub objects: Vec<Arc<DataObject>>,
-    result: BTreeResult,
 }
 
 impl Edge {
-    pub fn new(id: EdgeId, len: f32, ni: NodeId, nj: NodeId) -> Self {
+    pub fn new(id: GraphEdgeId, len: f32, ni: GraphNodeId, nj: GraphNodeId) -> Self {
         Edge {
             id,
             len,
             ni,
             nj,
+            oneway: false,
             objects: Vec::new(),
-            result: BTreeResult::new(),
+        }
+    }
+
+    /// Whether a traversal measuring distance in `direction` may go over
+    /// the edge starting from node `from`.
+    pub fn allows(&self, from: GraphNodeId, direction: Direction) -> bool {
+        match direction {
+            _ if !self.oneway => true,
+            Direction::FromObject => from == self.ni,
+            Direction::ToObject => from == self.nj,
+        }
+    }
+
+    /// Node at the other end of the edge from `n`
+    pub fn other(&self, n: GraphNodeId) -> GraphNodeId {
+        if n == self.ni {
+            self.nj
+        } else {
+            self.ni
         }
     }
 
@@ -53,28 +72,6 @@ impl Edge {
             })
             .collect()
     }
-
-    pub fn as_data_edge(&self) -> DataEdge {
-        DataEdge {
-            id: self.id,
-            ni: self.ni,
-            nj: self.nj,
-            len: self.len,
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-struct BTreeResult {
-    inner: HashMap<QueryId, BTreeMap<f32, ObjectId>>,
-}
-
-impl BTreeResult {
-    pub fn new() -> Self {
-        BTreeResult {
-            inner: HashMap::new(),
-        }
-    }
 }
 
 #[cfg(test)]
@@ -93,7 +90,8 @@ mod tests {
             })
         }
 
-        let mut e = Edge::new(1, 10.0, 1, 1);
+        let n = GraphNodeId::Original(1);
+        let mut e = Edge::new(GraphEdgeId::Original(1), 10.0, n, n);
         e.add_object(new_object(1.0));
         e.add_object(new_object(2.0));
         e.add_object(new_object(3.0));
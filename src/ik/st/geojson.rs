@@ -0,0 +1,175 @@
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+impl ResultVoronoi {
+    /// Zones for `key` as a GeoJSON `FeatureCollection`, one `LineString` or
+    /// `MultiLineString` feature per object of `object_ids`, or per object
+    /// having a zone if `None`. Edges are straight lines between their
+    /// `nodes`, edges with a node missing from `nodes` are left out.
+    pub fn to_geojson(
+        &self,
+        nodes: &[Arc<DataNode>],
+        key: &ZoneKey,
+        object_ids: Option<&[ObjectId]>,
+    ) -> Value {
+        let nodes: HashMap<NodeId, &DataNode> = nodes.iter().map(|n| (n.id, n.as_ref())).collect();
+
+        // pieces of each zone, consecutive segments of an edge joined
+        let mut zones: BTreeMap<ObjectId, Vec<(Arc<DataEdge>, f32, f32)>> = BTreeMap::new();
+        for (edge, start, end, segment_object_ids) in self.segments(key) {
+            for object_id in segment_object_ids {
+                if object_ids.is_some_and(|ids| !ids.contains(&object_id)) {
+                    continue;
+                }
+                let pieces = zones.entry(object_id).or_default();
+                match pieces.last_mut() {
+                    Some((last_edge, _, last_end))
+                        if last_edge.id == edge.id && *last_end == start =>
+                    {
+                        *last_end = end;
+                    }
+                    _ => pieces.push((edge.clone(), start, end)),
+                }
+            }
+        }
+
+        let mut features = Vec::new();
+        for (object_id, pieces) in zones {
+            let lines: Vec<Value> = pieces
+                .into_iter()
+                .filter_map(|(edge, start, end)| {
+                    let ni = nodes.get(&edge.ni)?;
+                    let nj = nodes.get(&edge.nj)?;
+                    Some(json!([
+                        Self::interpolate(ni, nj, start / edge.len),
+                        Self::interpolate(ni, nj, end / edge.len),
+                    ]))
+                })
+                .collect();
+            let geometry = match lines.len() {
+                0 => continue,
+                1 => json!({ "type": "LineString", "coordinates": lines[0] }),
+                _ => json!({ "type": "MultiLineString", "coordinates": lines }),
+            };
+            features.push(json!({
+                "type": "Feature",
+                "geometry": geometry,
+                "properties": {
+                    "object_id": object_id,
+                    "k": key.k,
+                    "dimensions": key.dimensions,
+                    "preferences": key.preferences,
+                },
+            }));
+        }
+
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+
+    /// Save `to_geojson` to `path`. JSON errors are returned as
+    /// `io::Error`.
+    pub fn save_geojson(
+        &self,
+        path: &Path,
+        nodes: &[Arc<DataNode>],
+        key: &ZoneKey,
+        object_ids: Option<&[ObjectId]>,
+    ) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &self.to_geojson(nodes, key, object_ids))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Position at `ratio` of the way from `ni` to `nj`, as `[lng, lat]`.
+    fn interpolate(ni: &DataNode, nj: &DataNode, ratio: f32) -> [f64; 2] {
+        let ratio = if ratio.is_finite() { ratio } else { 0.0 };
+        [
+            f64::from(ni.lng + (nj.lng - ni.lng) * ratio),
+            f64::from(ni.lat + (nj.lat - ni.lat) * ratio),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ik::fixture;
+
+    /// Lines of a `LineString` or `MultiLineString` feature, as pairs of
+    /// `[lng, lat]`.
+    fn lines(feature: &Value) -> Vec<[[f64; 2]; 2]> {
+        let geometry = &feature["geometry"];
+        let lines = match geometry["type"].as_str().unwrap() {
+            "LineString" => vec![geometry["coordinates"].clone()],
+            "MultiLineString" => geometry["coordinates"].as_array().unwrap().clone(),
+            t => panic!("unexpected geometry {}", t),
+        };
+        lines
+            .into_iter()
+            .map(|line| serde_json::from_value(line).unwrap())
+            .collect()
+    }
+
+    /// Whether lines `a` and `b` lie on the same straight line and share
+    /// more than an end.
+    fn is_overlapping(a: [[f64; 2]; 2], b: [[f64; 2]; 2]) -> bool {
+        let (dx, dy) = (a[1][0] - a[0][0], a[1][1] - a[0][1]);
+        let len_sq = dx * dx + dy * dy;
+        let cross = |p: [f64; 2]| dx * (p[1] - a[0][1]) - dy * (p[0] - a[0][0]);
+        if len_sq == 0.0 || cross(b[0]).abs() > 1e-6 || cross(b[1]).abs() > 1e-6 {
+            return false;
+        }
+        // offsets of `b` along `a`, as fractions of `a`
+        let along = |p: [f64; 2]| ((p[0] - a[0][0]) * dx + (p[1] - a[0][1]) * dy) / len_sq;
+        let (b0, b1) = (along(b[0]), along(b[1]));
+        b0.max(b1).min(1.0) - b0.min(b1).max(0.0) > 1e-6
+    }
+
+    #[test]
+    fn to_geojson() {
+        let (graph, queries) = fixture::graph_and_queries();
+        let result = ZoneBuilder::new(&graph, &queries).build();
+        let reader = Reader::new(Arc::new(AppConfig::default()));
+        let nodes = reader.read_node_csv().unwrap();
+
+        let key = ZoneKey::new(&[1, 2, 3, 4], &[Preference::Max; 4], 3);
+        let geojson = result.to_geojson(&nodes, &key, None);
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), result.object_ids(&key).len());
+        for feature in features {
+            assert_eq!(feature["properties"]["k"], 3);
+            assert_eq!(feature["properties"]["dimensions"], json!([1, 2, 3, 4]));
+            let lines = lines(feature);
+            for (i, a) in lines.iter().enumerate() {
+                for b in &lines[i + 1..] {
+                    assert!(!is_overlapping(*a, *b), "{:?} overlaps {:?}", a, b);
+                }
+            }
+        }
+
+        // an object listed twice in a segment, as in snapshots of older
+        // builds, makes no more pieces
+        let mut snapshot = result.to_snapshot();
+        for zones in &mut snapshot.zones {
+            for edge_result in &mut zones.results {
+                for (_, object_ids) in &mut edge_result.breakpoints {
+                    object_ids.extend(object_ids.clone());
+                }
+            }
+        }
+        let doubled = ResultVoronoi::from_snapshot(snapshot).unwrap();
+        assert_eq!(doubled.to_geojson(&nodes, &key, None), geojson);
+
+        let geojson = result.to_geojson(&nodes, &key, Some(&[2]));
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0]["properties"]["object_id"], 2);
+    }
+}
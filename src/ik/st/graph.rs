@@ -1,7 +1,6 @@
 use crate::prelude::*;
-use petgraph::graphmap::{GraphMap, Neighbors, Nodes};
-use petgraph::Undirected;
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -10,75 +9,94 @@ pub struct Graph {
     objects: HashMap<ObjectId, Arc<DataObject>>,
     map_nodes: HashMap<NodeId, Arc<DataNode>>,
     map_edges: HashMap<EdgeId, Arc<DataEdge>>,
-    map_new_edge: HashMap<EdgeId, Vec<EdgeId>>,
-    map_new_node: HashMap<EdgeId, Vec<NodeId>>,
-    inner: GraphMap<NodeId, Edge, Undirected>,
+    edges: HashMap<GraphEdgeId, Edge>,
+    /// Edges of each node, two one-way edges may join the same nodes
+    adjacency: HashMap<GraphNodeId, Vec<GraphEdgeId>>,
 }
 
 impl Graph {
-    pub fn new(config: Arc<AppConfig>) -> Self {
-        let graph = GraphMap::new();
+    pub fn new(config: Arc<AppConfig>) -> Result<Self, ReaderError> {
         let mut itself = Graph {
             config,
             objects: HashMap::new(),
             map_nodes: HashMap::new(),
             map_edges: HashMap::new(),
-            map_new_edge: HashMap::new(),
-            map_new_node: HashMap::new(),
-            inner: graph,
+            edges: HashMap::new(),
+            adjacency: HashMap::new(),
         };
-        itself.initial_network(true);
-        itself
+        itself.initial_network(true)?;
+        Ok(itself)
     }
 
-    pub fn new_empty_object(config: Arc<AppConfig>) -> Self {
-        let graph = GraphMap::new();
+    pub fn new_empty_object(config: Arc<AppConfig>) -> Result<Self, ReaderError> {
         let mut itself = Graph {
             config,
             objects: HashMap::new(),
             map_nodes: HashMap::new(),
             map_edges: HashMap::new(),
-            map_new_edge: HashMap::new(),
-            map_new_node: HashMap::new(),
-            inner: graph,
+            edges: HashMap::new(),
+            adjacency: HashMap::new(),
         };
-        itself.initial_network(false);
-        itself
+        itself.initial_network(false)?;
+        Ok(itself)
     }
 
-    fn initial_network(&mut self, with_objects: bool) {
+    fn initial_network(&mut self, with_objects: bool) -> Result<(), ReaderError> {
         let reader = Reader::new(self.config.clone());
 
-        let arc_nodes = reader.read_node_csv();
+        let arc_nodes = reader.read_node_csv()?;
         self.map_nodes = arc_nodes.iter().map(|a| (a.id, a.clone())).collect();
 
-        let edges = reader.read_edge_csv(&arc_nodes);
+        let edges = reader.read_edge_csv(&arc_nodes)?;
         self.insert_edges(edges);
 
         if with_objects {
-            let objects = reader.read_object_csv();
+            let objects = self.read_objects(&self.config.paths.object_path)?;
+            for object in &objects {
+                if !self.map_edges.contains_key(&object.edge_id) {
+                    return Err(ReaderError::UnknownEdge {
+                        path: self.config.paths.object_path.clone(),
+                        object_id: object.id,
+                        edge_id: object.edge_id,
+                    });
+                }
+            }
             self.insert_objects(objects);
         }
+        Ok(())
+    }
+
+    /// Read a file in the object format, snapping objects located by
+    /// coordinates to the network.
+    pub fn read_objects(&self, path: &Path) -> Result<Vec<Arc<DataObject>>, ReaderError> {
+        let reader = Reader::new(self.config.clone());
+        if reader.has_coordinates(path)? {
+            reader.read_object_csv_snapped(path, &SpatialIndex::from_graph(self))
+        } else {
+            reader.read_object_csv_at(path)
+        }
     }
 
     fn insert_edges(&mut self, edges: Vec<Arc<DataEdge>>) {
         for edge in edges {
-            self.inner.add_edge(
-                edge.ni,
-                edge.nj,
-                Edge::new(edge.id, edge.len, edge.ni, edge.nj),
-            );
+            let ni = GraphNodeId::Original(edge.ni);
+            let nj = GraphNodeId::Original(edge.nj);
+            let id = GraphEdgeId::Original(edge.id);
+            let mut new_edge = Edge::new(id, edge.len, ni, nj);
+            new_edge.oneway = edge.oneway;
+            self.edges.insert(id, new_edge);
+            self.adjacency.entry(ni).or_default().push(id);
+            if ni != nj {
+                self.adjacency.entry(nj).or_default().push(id);
+            }
             self.map_edges.insert(edge.id, edge);
         }
     }
 
     fn insert_objects(&mut self, objects: Vec<Arc<DataObject>>) {
         for object in objects {
-            let edge_data = self.map_edges.get(&object.edge_id).unwrap();
-            let edge = self
-                .inner
-                .edge_weight_mut(edge_data.ni, edge_data.nj)
-                .unwrap();
+            let edge_id = GraphEdgeId::Original(object.edge_id);
+            let edge = self.edges.get_mut(&edge_id).unwrap();
             edge.add_object(object.clone());
             self.objects.insert(object.id, object);
         }
@@ -86,12 +104,8 @@ impl Graph {
 
     pub fn remove_object(&mut self, object_id: ObjectId) {
         let object = self.objects.remove(&object_id).unwrap();
-        let edge_id = object.edge_id;
-        let data_edge = self.map_edges.get(&edge_id).unwrap();
-        let edge = self
-            .inner
-            .edge_weight_mut(data_edge.ni, data_edge.nj)
-            .unwrap();
+        let edge_id = GraphEdgeId::Original(object.edge_id);
+        let edge = self.edges.get_mut(&edge_id).unwrap();
         edge.remove_object(object_id);
     }
 
@@ -99,239 +113,59 @@ impl Graph {
         self.insert_objects(vec![object]);
     }
 
-    pub fn convert_object_as_node(&mut self, object: Arc<DataObject>) -> NodeId {
-        let (_, new_node_ids) = self.convert_objects_as_node_in_edge(object.edge_id, vec![object]);
-        *new_node_ids.first().unwrap()
-    }
-
-    #[allow(dead_code)]
-    pub fn convert_objects_to_node(
-        &mut self,
-        objects: Vec<Arc<DataObject>>,
-    ) -> HashMap<EdgeId, Vec<NodeId>> {
-        self.clean();
-        let mut map_edge_id: HashMap<EdgeId, Vec<Arc<DataObject>>> = HashMap::new();
-
-        for o in objects {
-            if let Some(vec_arc) = map_edge_id.get_mut(&o.edge_id) {
-                vec_arc.push(o);
-            } else {
-                map_edge_id.insert(o.edge_id, vec![o]);
-            }
-        }
-
-        let mut map_new_edge = HashMap::new();
-        for (edge_id, vec_arc) in map_edge_id {
-            let (vec_edge, _vec_node) = self.convert_objects_as_node_in_edge(edge_id, vec_arc);
-            map_new_edge.insert(edge_id, vec_edge);
-        }
-        map_new_edge
-    }
-
-    pub fn convert_object_ids_to_node(&mut self, object_ids: Vec<NodeId>) -> Vec<CentroidId> {
-        let mut objects = Vec::new();
-        for object_id in object_ids {
-            let object = self.objects.get(&object_id).unwrap().clone();
-            objects.push(object);
-        }
-        self.convert_objects_to_node(objects);
-        let mut centroid_ids = Vec::new();
-        for (_, vec_node) in &mut self.map_new_node.clone() {
-            centroid_ids.append(vec_node);
-        }
-        centroid_ids
-    }
-
-    fn convert_objects_as_node_in_edge(
-        &mut self,
-        edge_id: EdgeId,
-        mut objects: Vec<Arc<DataObject>>,
-    ) -> (Vec<EdgeId>, Vec<NodeId>) {
-        let edge = self.map_edges.get(&edge_id).unwrap();
-        let edge = self.inner.edge_weight(edge.ni, edge.nj).unwrap().clone();
-        objects.sort_by(|a, b| a.dist.partial_cmp(&b.dist).unwrap());
-
-        let last_object = objects.last();
-
-        let mut prev_node_id = edge.ni;
-        let last_node_id = edge.nj;
-        let mut new_edge_ids = Vec::new();
-        let mut new_node_ids = Vec::new();
-        let mut prev_dist = 0.0;
-
-        for o in &objects {
-            let new_node_id = Self::as_node_id(o);
-            self.inner.add_node(new_node_id);
-            new_node_ids.push(new_node_id);
-
-            // keep new node id mapped
-            let node = self.create_node_from_object(new_node_id, o.dist, &edge);
-            self.map_nodes.insert(node.id, node);
-
-            // insert new edge before object
-            let prev_edge_id = Self::object_as_edge_id(o.id);
-            let objects = edge.objects_in_between(prev_dist, o.dist);
-            self.add_edge(prev_edge_id, prev_node_id, new_node_id, objects);
-            new_edge_ids.push(prev_edge_id);
-
-            // insert new edge after object
-            if last_object.unwrap().id == o.id {
-                let next_edge_id = Self::object_as_last_edge_id(o.id);
-                let objects = edge.objects_in_between(o.dist, 1.0);
-                self.add_edge(next_edge_id, new_node_id, last_node_id, objects);
-                new_edge_ids.push(next_edge_id);
-            }
-
-            prev_node_id = new_node_id;
-            prev_dist = o.dist;
-        }
-
-        self.map_new_edge.insert(edge_id, new_edge_ids.clone());
-        self.map_new_node.insert(edge_id, new_node_ids.clone());
-
-        (new_edge_ids, new_node_ids)
-    }
-
-    fn create_node_from_object(
-        &self,
-        node_id: NodeId,
-        object_dist: f32,
-        edge: &Edge,
-    ) -> Arc<DataNode> {
-        let ni = self.map_nodes.get(&edge.ni).unwrap();
-        let nj = self.map_nodes.get(&edge.nj).unwrap();
-        let lng = (nj.lng - ni.lng) * object_dist + ni.lng;
-        let lat = (nj.lat - ni.lat) * object_dist + ni.lat;
-        Arc::new(DataNode {
-            id: node_id,
-            lng,
-            lat,
-        })
-    }
-
-    fn add_edge(
-        &mut self,
-        edge_id: EdgeId,
-        prev_node_id: NodeId,
-        node_id: NodeId,
-        objects: Vec<Arc<DataObject>>,
-    ) {
-        let edge_len = self.node_distance(prev_node_id, node_id);
-        let mut new_edge = Edge::new(edge_id, edge_len, prev_node_id, node_id);
-        new_edge.add_objects(objects);
-        let data_edge = new_edge.as_data_edge();
-        self.map_edges.insert(data_edge.id, Arc::new(data_edge));
-        self.inner.add_edge(prev_node_id, node_id, new_edge);
+    /// Edges joining `n` with its neighbors, in either direction
+    pub fn edges(&self, n: GraphNodeId) -> impl Iterator<Item = &Edge> {
+        self.adjacency
+            .get(&n)
+            .into_iter()
+            .flatten()
+            .map(move |id| self.edges.get(id).unwrap())
     }
 
-    fn node_distance(&self, a: NodeId, b: NodeId) -> f32 {
-        let a = self.map_nodes.get(&a).unwrap();
-        let b = self.map_nodes.get(&b).unwrap();
-        let lng = a.lng - b.lng;
-        let lat = a.lat - b.lat;
-        (lng * lng + lat * lat).sqrt()
-    }
-
-    pub fn neighbors(&self, n: NodeId) -> Neighbors<NodeId> {
-        self.inner.neighbors(n)
-    }
-
-    /// Remove node and its adjacent edges. Remove their indices too.
-    pub fn remove_node(&mut self, n: NodeId) {
-        let edges = self.inner.edges(n);
-        for (_, _, e) in edges {
-            self.map_edges.remove(&e.id);
-        }
-        self.map_nodes.remove(&n);
-        self.inner.remove_node(n);
+    pub fn object(&self, object_id: ObjectId) -> Arc<DataObject> {
+        self.objects.get(&object_id).unwrap().clone()
     }
 
-    pub fn clean(&mut self) {
-        for (_, node_ids) in self.map_new_node.clone() {
-            for node_id in node_ids {
-                self.remove_node(node_id)
-            }
-        }
-        self.map_new_edge = HashMap::new();
-        self.map_new_node = HashMap::new();
+    pub fn has_object(&self, object_id: ObjectId) -> bool {
+        self.objects.contains_key(&object_id)
     }
 
-    pub fn object(&self, object_id: ObjectId) -> Arc<DataObject> {
-        self.objects.get(&object_id).unwrap().clone()
+    pub fn has_edge(&self, edge_id: EdgeId) -> bool {
+        self.map_edges.contains_key(&edge_id)
     }
 
     pub fn all_objects(&self) -> Vec<Arc<DataObject>> {
-        self.objects.iter().map(|(_, o)| o.clone()).collect()
-    }
-
-    pub fn nodes(&self) -> Nodes<NodeId> {
-        self.inner.nodes()
+        self.objects.values().cloned().collect()
     }
 
-    pub fn edge_len(&self, a: NodeId, b: NodeId) -> f32 {
-        let edge = self.inner.edge_weight(a, b).unwrap();
-        edge.len
+    pub fn nodes(&self) -> impl Iterator<Item = GraphNodeId> + '_ {
+        self.adjacency.keys().copied()
     }
 
-    pub fn edge_id(&self, a: NodeId, b: NodeId) -> Option<EdgeId> {
-        if let Some(edge) = self.inner.edge_weight(a, b) {
-            Some(edge.id)
-        } else {
-            None
-        }
+    pub fn edge(&self, edge_id: GraphEdgeId) -> Option<&Edge> {
+        self.edges.get(&edge_id)
     }
 
-    pub fn edge(&self, a: NodeId, b: NodeId) -> Option<&Edge> {
-        self.inner.edge_weight(a, b)
+    pub fn node_by_node_id(&self, n: NodeId) -> Arc<DataNode> {
+        self.map_nodes.get(&n).unwrap().clone()
     }
 
     pub fn edge_by_edge_id(&self, e: EdgeId) -> Arc<DataEdge> {
         self.map_edges.get(&e).unwrap().clone()
     }
 
-    pub fn map_edges(&self) -> HashMap<EdgeId, Arc<DataEdge>> {
-        self.map_edges.clone()
-    }
-
-    pub fn objects(&self, a: NodeId, b: NodeId) -> Vec<Arc<DataObject>> {
-        let edge_weight = self.inner.edge_weight(a, b).unwrap();
-        edge_weight.objects.clone()
-    }
-
-    pub fn map_new_edge(&self) -> HashMap<EdgeId, Vec<EdgeId>> {
-        self.map_new_edge.clone()
-    }
-
-    pub fn map_new_node(&self) -> HashMap<EdgeId, Vec<NodeId>> {
-        self.map_new_node.clone()
-    }
-
-    pub fn all_edge_ids(&self) -> Vec<EdgeId> {
-        self.inner.all_edges().map(|(_, _, e)| e.id).collect()
+    /// Nodes of the dataset
+    pub fn map_nodes(&self) -> HashMap<NodeId, Arc<DataNode>> {
+        self.map_nodes.clone()
     }
 
-    fn as_node_id(o: &Arc<DataObject>) -> NodeId {
-        o.id + 100000
-    }
-
-    pub fn as_centroid_id(object_id: ObjectId) -> CentroidId {
-        object_id + 100000
-    }
-
-    pub fn as_object_id(node_id: NodeId) -> ObjectId {
-        if node_id > 100000 {
-            node_id - 100000
-        } else {
-            node_id
-        }
-    }
-
-    fn object_as_edge_id(object_id: ObjectId) -> EdgeId {
-        object_id + 200000
+    /// Edges of the dataset
+    pub fn map_edges(&self) -> HashMap<EdgeId, Arc<DataEdge>> {
+        self.map_edges.clone()
     }
 
-    fn object_as_last_edge_id(object_id: ObjectId) -> EdgeId {
-        object_id + 300000
+    pub fn all_edge_ids(&self) -> Vec<GraphEdgeId> {
+        self.edges.keys().copied().collect()
     }
 }
 
@@ -342,59 +176,65 @@ mod tests {
     #[test]
     fn graph_new() {
         let conf = Arc::new(AppConfig::default());
-        let graph = Graph::new(conf);
-        let nodes = graph.inner.nodes().into_iter().count();
+        let graph = Graph::new(conf).unwrap();
+        let nodes = graph.nodes().count();
         assert_eq!(nodes, 6);
 
-        let edges = graph.inner.all_edges().into_iter().count();
+        let edges = graph.all_edge_ids().len();
         assert_eq!(edges, 5);
     }
 
     #[test]
-    fn convert_objects_as_node() {
-        let conf = Arc::new(AppConfig::default());
-        let mut graph = Graph::new(conf);
-
-        fn new_object(id: ObjectId, edge_id: EdgeId, dist: f32) -> Arc<DataObject> {
-            let o = DataObject {
-                id,
-                edge_id,
-                dist,
-                attr: Vec::new(),
-                action: Action::Insertion,
-            };
-            Arc::new(o)
-        }
-
-        let objects = vec![
-            new_object(100, 3, 0.3),
-            new_object(101, 3, 0.4),
-            new_object(102, 3, 0.8),
-        ];
-
-        let (_new_edge_ids, new_node_ids) = graph.convert_objects_as_node_in_edge(3, objects);
-
-        assert_eq!(new_node_ids.len(), 3);
-
-        let edges = graph.inner.all_edges();
-        let (mut e1, mut e2, mut e3) = (false, false, false);
-        for (ni, nj, edge) in edges {
-            if edge.id == Graph::object_as_edge_id(100) {
-                e1 = true;
-            } else if edge.id == Graph::object_as_edge_id(101) {
-                e2 = true;
-            } else if edge.id == Graph::object_as_last_edge_id(102) {
-                e3 = true;
+    fn graph_opposite_edges() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("node.txt"), "id lng lat\n1 0.0 0.0\n2 3.0 4.0\n").unwrap();
+        let conf = Arc::new(AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            weight: Some(String::from("length")),
+            ..Default::default()
+        });
+
+        // a street open both ways, 5 long one way and 7 the other
+        std::fs::write(
+            dir.join("edge.txt"),
+            "id node_i node_j length oneway\n1 1 2 5.0 1\n2 2 1 7.0 1\n",
+        )
+        .unwrap();
+        let graph = Graph::new_empty_object(conf.clone()).unwrap();
+        let mut edges: Vec<(GraphEdgeId, f32)> = graph
+            .edges(GraphNodeId::Original(1))
+            .map(|e| (e.id, e.len))
+            .collect();
+        edges.sort_by_key(|(id, _)| *id);
+        assert_eq!(
+            edges,
+            vec![
+                (GraphEdgeId::Original(1), 5.0),
+                (GraphEdgeId::Original(2), 7.0),
+            ]
+        );
+
+        // no third edge, nor two edges of the same direction
+        for (edges, edge_id, other_id) in &[
+            ("1 1 2 5.0 1\n2 2 1 7.0 1\n3 1 2 5.0 0\n", 3, 1),
+            ("1 1 2 5.0 1\n2 1 2 7.0 1\n", 2, 1),
+            ("1 1 2 5.0 0\n2 2 1 7.0 1\n", 2, 1),
+        ] {
+            let text = format!("id node_i node_j length oneway\n{}", edges);
+            std::fs::write(dir.join("edge.txt"), text).unwrap();
+            match Graph::new_empty_object(conf.clone()) {
+                Err(ReaderError::DuplicateEdge {
+                    line,
+                    edge_id: id,
+                    other_id: other,
+                    ..
+                }) => assert_eq!(
+                    (line, id, other),
+                    (*edge_id as u64 + 1, *edge_id, *other_id)
+                ),
+                r => panic!("unexpected {:?}", r.map(|_| ())),
             }
-
-            println!(
-                "EdgeID {} \t NodeI {} \t NodeJ {} \t Length {}",
-                edge.id, ni, nj, edge.len
-            );
-
-            println!("Objects: {:#?}", edge.objects);
         }
-
-        assert!(e1 == true && e2 == true && e3 == true)
     }
 }
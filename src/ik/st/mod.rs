@@ -1,4 +1,9 @@
 pub mod edge;
+pub mod geojson;
 pub mod graph;
 pub mod node;
 pub mod result;
+pub mod snapshot;
+pub mod spatial;
+pub mod stats;
+pub mod view;
@@ -1,65 +1,447 @@
+use super::snapshot::{
+    Snapshot, SnapshotEdge, SnapshotEdgeResult, SnapshotZones, SNAPSHOT_VERSION,
+};
 use crate::prelude::*;
 use ordered_float::OrderedFloat as OF;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Bound::{Excluded, Included};
 use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct ResultVoronoi {
-    inner: HashMap<EdgeId, HashMap<K, EdgeResult>>,
+    inner: HashMap<ZoneKey, HashMap<EdgeId, EdgeResult>>,
+    /// Inverted index of `inner`, ranges of each object by edge
+    zones: HashMap<ZoneKey, HashMap<ObjectId, Vec<(EdgeId, Range)>>>,
     edges: HashMap<EdgeId, Arc<DataEdge>>,
+    node_edges: HashMap<NodeId, Vec<EdgeId>>,
+}
+
+/// Zones shared by the queries over the same dimensions, with the same
+/// preferences, for the same k.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct ZoneKey {
+    pub dimensions: Vec<DimensionIndex>,
+    /// Preference of each of `dimensions`
+    pub preferences: Vec<Preference>,
+    pub k: K,
+}
+
+/// A spot on the network to look up in `ResultVoronoi`.
+#[derive(Copy, Clone, Debug)]
+pub enum Location {
+    /// Point on an edge, `offset` measured from node i of the edge.
+    Edge(EdgeId, f32),
+    Node(NodeId),
+}
+
+impl ZoneKey {
+    pub fn new(dimensions: &[DimensionIndex], preferences: &[Preference], k: K) -> Self {
+        debug_assert_eq!(dimensions.len(), preferences.len());
+        ZoneKey {
+            dimensions: dimensions.to_vec(),
+            preferences: preferences.to_vec(),
+            k,
+        }
+    }
+}
+
+impl From<&Query> for ZoneKey {
+    fn from(query: &Query) -> Self {
+        ZoneKey::new(&query.dimensions, &query.preferences, query.k)
+    }
+}
+
+impl fmt::Display for ZoneKey {
+    /// As `k 3 of 1,2:min,4`, in the syntax of the query file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "k {} of ", self.k)?;
+        for (i, (d, preference)) in self.dimensions.iter().zip(&self.preferences).enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            match preference {
+                Preference::Max => write!(f, "{}{}", separator, d)?,
+                Preference::Min => write!(f, "{}{}:min", separator, d)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 impl ResultVoronoi {
     pub fn from_edge_ids(edges: HashMap<EdgeId, Arc<DataEdge>>) -> Self {
-        let result = ResultVoronoi {
+        let mut node_edges: HashMap<NodeId, Vec<EdgeId>> = HashMap::new();
+        for edge in edges.values() {
+            node_edges.entry(edge.ni).or_default().push(edge.id);
+            node_edges.entry(edge.nj).or_default().push(edge.id);
+        }
+
+        ResultVoronoi {
             inner: HashMap::new(),
+            zones: HashMap::new(),
             edges,
-        };
-
-        result
+            node_edges,
+        }
     }
 
     /// insert voronoi scope in `edge_id`
-    pub fn insert(&mut self, k: K, edge_id: EdgeId, ranges: Vec<Range>) {
-        let edge = self.edges.get(&edge_id).unwrap();
+    pub fn insert(&mut self, key: &ZoneKey, edge_id: EdgeId, ranges: Vec<Range>) {
+        let edge_len = self.edges.get(&edge_id).unwrap().len;
+        if !self.inner.contains_key(key) {
+            self.inner.insert(key.clone(), HashMap::new());
+        }
+        self.index(key, edge_id, &ranges);
+        let edge_result = self
+            .inner
+            .get_mut(key)
+            .unwrap()
+            .entry(edge_id)
+            .or_insert_with(|| EdgeResult::new(edge_len));
+        for r in ranges {
+            edge_result.insert(r);
+        }
+    }
+
+    /// Add `ranges` of `edge_id` to the zones of their objects.
+    fn index(&mut self, key: &ZoneKey, edge_id: EdgeId, ranges: &[Range]) {
+        if !self.zones.contains_key(key) {
+            self.zones.insert(key.clone(), HashMap::new());
+        }
+        let zones = self.zones.get_mut(key).unwrap();
+        for r in ranges {
+            zones.entry(r.centroid_id).or_default().push((edge_id, *r));
+        }
+    }
 
-        if let Some(k_edge_result) = self.inner.get_mut(&edge_id) {
-            if let Some(edge_result) = k_edge_result.get_mut(&k) {
-                for r in ranges {
-                    edge_result.insert(r);
+    /// Remove the zone of `object_id` for `key`, visiting only the edges
+    /// it covers.
+    pub fn remove(&mut self, object_id: ObjectId, key: &ZoneKey) {
+        let zone = self
+            .zones
+            .get_mut(key)
+            .and_then(|zones| zones.remove(&object_id));
+        if let (Some(zone), Some(edge_results)) = (zone, self.inner.get_mut(key)) {
+            for (edge_id, _) in zone {
+                if let Some(edge_result) = edge_results.get_mut(&edge_id) {
+                    edge_result.remove(object_id);
                 }
-            } else {
-                let mut edge_result = EdgeResult::new(edge.len);
-                for r in ranges {
-                    edge_result.insert(r);
+            }
+        }
+    }
+
+    /// Remove zones of every object for `key`, whether there were any.
+    pub fn remove_key(&mut self, key: &ZoneKey) -> bool {
+        self.zones.remove(key);
+        self.inner.remove(key).is_some()
+    }
+
+    pub fn remove_all(&mut self, object_id: ObjectId) {
+        for key in self.keys() {
+            self.remove(object_id, &key);
+        }
+    }
+
+    /// Ranges of the zone of `object_id` for `key`, with their edge, in the
+    /// order they were inserted.
+    pub fn zone(&self, object_id: ObjectId, key: &ZoneKey) -> &[(EdgeId, Range)] {
+        self.zones
+            .get(key)
+            .and_then(|zones| zones.get(&object_id))
+            .map(|zone| zone.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Edges the zone of `object_id` for `key` covers, in ascending order.
+    pub fn zone_edges(&self, object_id: ObjectId, key: &ZoneKey) -> Vec<EdgeId> {
+        let mut edge_ids: Vec<EdgeId> = self
+            .zone(object_id, key)
+            .iter()
+            .filter(|(_, r)| r.end > r.start)
+            .map(|(edge_id, _)| *edge_id)
+            .collect();
+        edge_ids.sort_unstable();
+        edge_ids.dedup();
+        edge_ids
+    }
+
+    /// Network length the zone of `object_id` for `key` covers, counting
+    /// overlapping ranges once.
+    pub fn zone_length(&self, object_id: ObjectId, key: &ZoneKey) -> f32 {
+        let mut ranges: Vec<(EdgeId, f32, f32)> = self
+            .zone(object_id, key)
+            .iter()
+            .map(|(edge_id, r)| {
+                let len = self.edges[edge_id].len;
+                (*edge_id, r.start.max(0.0), r.end.min(len))
+            })
+            .filter(|(_, start, end)| end > start)
+            .collect();
+        ranges.sort_by(|a, b| (a.0, a.1).partial_cmp(&(b.0, b.1)).unwrap());
+
+        let mut length = 0.0;
+        let mut covered: Option<(EdgeId, f32)> = None;
+        for (edge_id, start, end) in ranges {
+            let start = match covered {
+                Some((covered_edge_id, covered_end)) if covered_edge_id == edge_id => {
+                    start.max(covered_end)
                 }
-                k_edge_result.insert(k, edge_result);
+                _ => start,
+            };
+            if end > start {
+                length += end - start;
+                covered = Some((edge_id, end));
+            }
+        }
+        length
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Edges of the network the zones are on
+    pub fn edges(&self) -> impl Iterator<Item = &Arc<DataEdge>> {
+        self.edges.values()
+    }
+
+    /// Keys having zones, in ascending order.
+    pub fn keys(&self) -> Vec<ZoneKey> {
+        let mut keys: Vec<ZoneKey> = self.inner.keys().cloned().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    pub fn has_key(&self, key: &ZoneKey) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Objects having a zone for `key`, in ascending order.
+    pub fn object_ids(&self, key: &ZoneKey) -> Vec<ObjectId> {
+        let mut object_ids: Vec<ObjectId> = self
+            .zones
+            .get(key)
+            .into_iter()
+            .flat_map(|zones| zones.keys().copied())
+            .collect();
+        object_ids.sort_unstable();
+        object_ids
+    }
+
+    /// Move zones of `other` into this result, both covering the same edges.
+    pub fn merge(&mut self, other: ResultVoronoi) {
+        for (key, edge_results) in other.inner {
+            for (edge_id, edge_result) in edge_results {
+                self.insert(&key, edge_id, edge_result.ranges);
             }
+        }
+    }
+
+    /// Objects whose zone for `key` covers the point at `offset` of
+    /// `edge_id`.
+    pub fn objects_at(&self, edge_id: EdgeId, offset: f32, key: &ZoneKey) -> Vec<ObjectId> {
+        let edge_result = self.inner.get(key).and_then(|r| r.get(&edge_id));
+        if let Some(edge_result) = edge_result {
+            edge_result.centroids_at(offset)
         } else {
-            let mut hash = HashMap::new();
-            let mut edge_result = EdgeResult::new(edge.len);
-            for r in ranges {
-                edge_result.insert(r);
+            Vec::new()
+        }
+    }
+
+    /// Objects whose zone for `key` covers `node_id`.
+    pub fn objects_at_node(&self, node_id: NodeId, key: &ZoneKey) -> Vec<ObjectId> {
+        let mut object_ids = Vec::new();
+        if let Some(edge_ids) = self.node_edges.get(&node_id) {
+            for edge_id in edge_ids {
+                let edge = self.edges.get(edge_id).unwrap();
+                let offset = if edge.ni == node_id { 0.0 } else { edge.len };
+                object_ids.append(&mut self.objects_at(*edge_id, offset, key));
             }
-            hash.insert(k, edge_result);
-            self.inner.insert(edge_id, hash);
+        }
+        object_ids.sort_unstable();
+        object_ids.dedup();
+        object_ids
+    }
+
+    /// Objects whose zone for `key` covers `location`.
+    pub fn locate(&self, location: Location, key: &ZoneKey) -> Vec<ObjectId> {
+        match location {
+            Location::Edge(edge_id, offset) => self.objects_at(edge_id, offset, key),
+            Location::Node(node_id) => self.objects_at_node(node_id, key),
         }
     }
 
-    pub fn remove(&mut self, object_id: ObjectId, k: K) {
-        for (_edge_id, k_edge_result) in &mut self.inner {
-            if let Some(edge_result) = k_edge_result.get_mut(&k) {
-                edge_result.remove(object_id);
+    /// Batch version of `locate`, results are in the order of `locations`.
+    pub fn locate_all(&self, locations: &[Location], key: &ZoneKey) -> Vec<Vec<ObjectId>> {
+        locations.iter().map(|l| self.locate(*l, key)).collect()
+    }
+
+    /// Pieces of edges between breakpoints of the zones for `key`, as
+    /// `(edge, start, end, object_ids)` ordered by edge id and offset, each
+    /// object listed once.
+    pub(crate) fn segments(&self, key: &ZoneKey) -> Vec<(Arc<DataEdge>, f32, f32, Vec<ObjectId>)> {
+        let edge_results = match self.inner.get(key) {
+            Some(edge_results) => edge_results,
+            None => return Vec::new(),
+        };
+        let mut edge_ids: Vec<EdgeId> = edge_results.keys().copied().collect();
+        edge_ids.sort_unstable();
+
+        let mut segments = Vec::new();
+        for edge_id in edge_ids {
+            let edge = self.edges[&edge_id].clone();
+            let mut start = 0.0;
+            for (end, object_ids) in &edge_results[&edge_id].inner {
+                if !object_ids.is_empty() {
+                    // snapshots of older builds may list an object twice
+                    let mut object_ids = object_ids.clone();
+                    object_ids.sort_unstable();
+                    object_ids.dedup();
+                    segments.push((edge.clone(), start, end.0, object_ids));
+                }
+                start = end.0;
             }
         }
+        segments
     }
 
-    pub fn remove_all(&mut self, object_id: ObjectId) {
-        for (_edge_id, k_edge_result) in &mut self.inner {
-            for (_k, edge_result) in k_edge_result {
-                edge_result.remove(object_id);
+    pub(crate) fn to_snapshot(&self) -> Snapshot {
+        let mut edges: Vec<SnapshotEdge> = self
+            .edges
+            .values()
+            .map(|e| SnapshotEdge {
+                id: e.id,
+                ni: e.ni,
+                nj: e.nj,
+                len: e.len,
+            })
+            .collect();
+        edges.sort_by_key(|e| e.id);
+
+        let mut zones = Vec::new();
+        for key in self.keys() {
+            let mut results: Vec<SnapshotEdgeResult> = self.inner[&key]
+                .iter()
+                .map(|(edge_id, edge_result)| SnapshotEdgeResult {
+                    edge_id: *edge_id,
+                    edge_len: edge_result.edge_len,
+                    ranges: edge_result
+                        .ranges
+                        .iter()
+                        .map(|r| (r.start, r.end, r.centroid_id))
+                        .collect(),
+                    breakpoints: edge_result
+                        .inner
+                        .iter()
+                        .map(|(dist, object_ids)| (dist.0, object_ids.clone()))
+                        .collect(),
+                })
+                .collect();
+            results.sort_by_key(|r| r.edge_id);
+            zones.push(SnapshotZones {
+                dimensions: key.dimensions,
+                preferences: key.preferences,
+                k: key.k,
+                results,
+            });
+        }
+
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            edges,
+            zones,
+        }
+    }
+
+    /// Ranges and segment ends of `r` must lie on its edge of length `len`,
+    /// the last segment ending at node j.
+    fn check_snapshot_ranges(r: &SnapshotEdgeResult, len: f32) -> Result<(), SnapshotError> {
+        for &(start, end, _) in &r.ranges {
+            // false for NaN as well
+            if !(0.0 <= start && start <= end && end <= len) {
+                return Err(SnapshotError::InvalidRange {
+                    edge_id: r.edge_id,
+                    start,
+                    end,
+                });
             }
         }
+        let is_within = |dist: f32| 0.0 < dist && dist <= len;
+        if let Some(&(dist, _)) = r.breakpoints.iter().find(|(dist, _)| !is_within(*dist)) {
+            return Err(SnapshotError::InvalidBreakpoint {
+                edge_id: r.edge_id,
+                dist,
+            });
+        }
+        if r.edge_len != len || !r.breakpoints.iter().any(|(dist, _)| *dist == len) {
+            return Err(SnapshotError::InvalidBreakpoint {
+                edge_id: r.edge_id,
+                dist: r.edge_len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Rebuild from `snapshot`, every zone must be on one of its edges with
+    /// its ranges.
+    pub(crate) fn from_snapshot(snapshot: Snapshot) -> Result<Self, SnapshotError> {
+        let edges = snapshot
+            .edges
+            .into_iter()
+            .map(|e| {
+                let edge = DataEdge {
+                    id: e.id,
+                    ni: e.ni,
+                    nj: e.nj,
+                    len: e.len,
+                    // lookups do not depend on direction
+                    oneway: false,
+                };
+                (e.id, Arc::new(edge))
+            })
+            .collect();
+        let mut result = Self::from_edge_ids(edges);
+
+        for zones in snapshot.zones {
+            let key = ZoneKey {
+                dimensions: zones.dimensions,
+                preferences: zones.preferences,
+                k: zones.k,
+            };
+            let edge_results = zones
+                .results
+                .into_iter()
+                .map(|r| {
+                    let edge = result
+                        .edges
+                        .get(&r.edge_id)
+                        .ok_or(SnapshotError::UnknownEdge(r.edge_id))?;
+                    Self::check_snapshot_ranges(&r, edge.len)?;
+                    let edge_result = EdgeResult {
+                        ranges: r
+                            .ranges
+                            .into_iter()
+                            .map(|(start, end, centroid_id)| Range {
+                                start,
+                                end,
+                                centroid_id,
+                            })
+                            .collect(),
+                        edge_len: r.edge_len,
+                        inner: r
+                            .breakpoints
+                            .into_iter()
+                            .map(|(dist, object_ids)| (OF(dist), object_ids))
+                            .collect(),
+                    };
+                    Ok((r.edge_id, edge_result))
+                })
+                .collect::<Result<HashMap<EdgeId, EdgeResult>, SnapshotError>>()?;
+            for (edge_id, edge_result) in &edge_results {
+                result.index(&key, *edge_id, &edge_result.ranges);
+            }
+            result.inner.insert(key, edge_results);
+        }
+        Ok(result)
     }
 }
 
@@ -82,59 +464,67 @@ impl EdgeResult {
         }
     }
 
+    /// Each key of `inner` is the end of a segment, the segment starts at the
+    /// previous key (or 0.0) and is covered by the objects of its value.
     fn insert(&mut self, range: Range) {
-        self.ranges.push(range.clone());
+        self.ranges.push(range);
         let object_id = range.centroid_id;
-        let start = OF(range.start);
-        let end = OF(range.end);
-        let mut inner_clone = self.inner.clone();
-        let existing_ranges = self.inner.range(start..);
-        let mut range_iter = existing_ranges.into_iter().peekable();
+        let start = OF(range.start.max(0.0));
+        // ranges moved from split edges may stop a rounding error short of
+        // node j
+        let end = if self.edge_len - range.end < self.edge_len * 1e-5 {
+            OF(self.edge_len)
+        } else {
+            OF(range.end)
+        };
+        if start >= end {
+            return;
+        }
 
-        let mut first = true;
-        loop {
-            let next = range_iter.next();
-
-            if let Some((k, v)) = next {
-                if *k <= end {
-                    if first {
-                        inner_clone.insert(start, v.clone());
-                        first = false;
-                    }
-
-                    let object_ids = inner_clone.get_mut(k).unwrap();
-                    object_ids.push(object_id);
-                } else {
-                    let mut object_ids = inner_clone.get(k).unwrap().clone();
-                    object_ids.push(object_id);
-
-                    inner_clone.insert(end, object_ids);
-                    break;
-                }
-            } else {
-                break;
+        self.split_at(start);
+        self.split_at(end);
+        // an object covers a segment once, however many of its ranges do
+        for (_, object_ids) in self.inner.range_mut((Excluded(start), Included(end))) {
+            if !object_ids.contains(&object_id) {
+                object_ids.push(object_id);
             }
         }
+    }
+
+    /// Make `dist` a segment end, keeping the objects of the segment it falls in.
+    fn split_at(&mut self, dist: OF<f32>) {
+        if dist <= OF(0.0) || self.inner.contains_key(&dist) {
+            return;
+        }
+
+        if let Some((_, object_ids)) = self.inner.range(dist..).next() {
+            let object_ids = object_ids.clone();
+            self.inner.insert(dist, object_ids);
+        }
+    }
 
-        self.inner = inner_clone;
+    /// Centroids of the segments within a rounding error of `offset`, as
+    /// `insert` allows at node j, so ranges are closed at both ends.
+    fn centroids_at(&self, offset: f32) -> Vec<CentroidId> {
+        let offset = offset.max(0.0).min(self.edge_len);
+        let tolerance = self.edge_len * 1e-5;
+        let mut object_ids = Vec::new();
+        for (end, segment_ids) in self.inner.range(OF(offset - tolerance)..) {
+            object_ids.extend_from_slice(segment_ids);
+            // the next segment starts at `end`
+            if end.0 > offset + tolerance {
+                break;
+            }
+        }
+        object_ids.sort_unstable();
+        object_ids.dedup();
+        object_ids
     }
 
     fn remove(&mut self, object_id: CentroidId) {
         self.ranges.retain(|r| r.centroid_id != object_id);
-        let mut deleted_dist = HashSet::new();
-        for (dist, vec_object_id) in &mut self.inner {
-            let mut is_deleted = false;
-            vec_object_id.retain(|o| {
-                if o == &object_id {
-                    is_deleted = true;
-                    false
-                } else {
-                    true
-                }
-            });
-            if is_deleted {
-                deleted_dist.insert(*dist);
-            }
+        for vec_object_id in self.inner.values_mut() {
+            vec_object_id.retain(|o| *o != object_id);
             vec_object_id.sort();
         }
         let inner_clone = self.inner.clone();
@@ -167,6 +557,7 @@ impl Default for EdgeResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ik::fixture;
 
     #[test]
     fn edge_result() {
@@ -198,12 +589,204 @@ mod tests {
             edge_result.insert(range);
         }
 
-        println!("{:#?}", edge_result);
-        assert_eq!(edge_result.inner.len(), 6);
+        assert_eq!(edge_result.inner.len(), 7);
+        assert_eq!(edge_result.centroids_at(3.5), &[1, 2, 3, 4]);
 
         edge_result.remove(3);
-        println!("{:#?}", edge_result);
         assert_eq!(edge_result.ranges.len(), 3);
-        assert_eq!(edge_result.inner.len(), 4);
+        assert_eq!(edge_result.inner.len(), 5);
+        assert_eq!(edge_result.centroids_at(0.5), &[1]);
+        assert_eq!(edge_result.centroids_at(8.0), &[4]);
+        assert!(edge_result.centroids_at(9.5).is_empty());
+    }
+
+    #[test]
+    fn edge_result_boundaries() {
+        let mut edge_result = EdgeResult::new(10.0);
+        edge_result.insert(Range {
+            start: 0.0,
+            end: 4.0,
+            centroid_id: 1,
+        });
+        edge_result.insert(Range {
+            start: 4.0,
+            end: 10.0,
+            centroid_id: 2,
+        });
+
+        // starts a rounding error past 4.0
+        edge_result.insert(Range {
+            start: 4.0 + 1e-5,
+            end: 6.0,
+            centroid_id: 3,
+        });
+
+        assert_eq!(edge_result.centroids_at(0.0), &[1]);
+        assert_eq!(edge_result.centroids_at(4.0), &[1, 2, 3]);
+        assert_eq!(edge_result.centroids_at(5.0), &[2, 3]);
+        assert_eq!(edge_result.centroids_at(6.0), &[2, 3]);
+        assert_eq!(edge_result.centroids_at(7.0), &[2]);
+        assert_eq!(edge_result.centroids_at(10.0), &[2]);
+    }
+
+    #[test]
+    fn edge_result_overlapping_ranges() {
+        let mut edge_result = EdgeResult::new(10.0);
+        edge_result.insert(Range {
+            start: 0.0,
+            end: 4.0,
+            centroid_id: 1,
+        });
+        // ends where the first range ends
+        edge_result.insert(Range {
+            start: 2.0,
+            end: 4.0,
+            centroid_id: 2,
+        });
+        // starts inside the first range
+        edge_result.insert(Range {
+            start: 3.0,
+            end: 6.0,
+            centroid_id: 3,
+        });
+
+        assert_eq!(edge_result.inner[&OF(2.0)], vec![1]);
+        assert_eq!(edge_result.inner[&OF(3.0)], vec![1, 2]);
+        assert_eq!(edge_result.inner[&OF(4.0)], vec![1, 2, 3]);
+        assert_eq!(edge_result.inner[&OF(6.0)], vec![3]);
+        assert!(edge_result.inner[&OF(10.0)].is_empty());
+
+        // another range of object 1 over its first one
+        edge_result.insert(Range {
+            start: 1.0,
+            end: 5.0,
+            centroid_id: 1,
+        });
+        assert_eq!(edge_result.inner[&OF(1.0)], vec![1]);
+        assert_eq!(edge_result.inner[&OF(4.0)], vec![1, 2, 3]);
+        assert_eq!(edge_result.inner[&OF(5.0)], vec![3, 1]);
+        assert_eq!(edge_result.centroids_at(3.5), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn edge_result_end_at_node_j() {
+        let mut edge_result = EdgeResult::new(10.0);
+        edge_result.insert(Range {
+            start: 0.0,
+            end: 10.0 - 1e-6,
+            centroid_id: 1,
+        });
+
+        assert_eq!(edge_result.inner.len(), 1);
+        assert_eq!(edge_result.inner[&OF(10.0)], vec![1]);
+    }
+
+    #[test]
+    fn result_voronoi_locate() {
+        let mut edges = HashMap::new();
+        edges.insert(
+            1,
+            Arc::new(DataEdge {
+                id: 1,
+                ni: 1,
+                nj: 2,
+                len: 10.0,
+                oneway: false,
+            }),
+        );
+        edges.insert(
+            2,
+            Arc::new(DataEdge {
+                id: 2,
+                ni: 2,
+                nj: 3,
+                len: 5.0,
+                oneway: false,
+            }),
+        );
+        let mut result = ResultVoronoi::from_edge_ids(edges);
+
+        let range = |start, end, object_id| Range {
+            start,
+            end,
+            centroid_id: object_id,
+        };
+        let key = ZoneKey::new(&[1, 2, 3, 4], &[Preference::Max; 4], 3);
+        let other = ZoneKey::new(&[1, 2, 3], &[Preference::Max; 3], 3);
+        result.insert(&key, 1, vec![range(0.0, 6.0, 1), range(4.0, 10.0, 2)]);
+        result.insert(&key, 2, vec![range(0.0, 5.0, 2)]);
+        result.insert(&other, 1, vec![range(0.0, 10.0, 3)]);
+
+        assert_eq!(result.objects_at(1, 2.0, &key), vec![1]);
+        assert_eq!(result.objects_at(1, 5.0, &key), vec![1, 2]);
+        assert_eq!(result.objects_at(1, 4.0, &key), vec![1, 2]);
+        assert_eq!(result.objects_at(1, 5.0, &other), vec![3]);
+        let missing = ZoneKey::new(&[1, 2, 3, 4], &[Preference::Max; 4], 4);
+        assert!(result.objects_at(1, 5.0, &missing).is_empty());
+        assert_eq!(result.objects_at_node(1, &key), vec![1]);
+        assert_eq!(result.objects_at_node(2, &key), vec![2]);
+
+        let locations = [
+            Location::Edge(2, 1.0),
+            Location::Node(3),
+            Location::Edge(9, 0.0),
+        ];
+        let found = result.locate_all(&locations, &key);
+        assert_eq!(found, vec![vec![2], vec![2], vec![]]);
+
+        assert_eq!(result.edge_count(), 2);
+        assert_eq!(result.keys(), vec![other.clone(), key.clone()]);
+        assert_eq!(result.object_ids(&key), vec![1, 2]);
+        assert_eq!(other.to_string(), "k 3 of 1,2,3");
+
+        assert!(result.remove_key(&other));
+        assert_eq!(result.keys(), vec![key]);
+    }
+
+    #[test]
+    fn result_voronoi_zone() {
+        let (graph, queries) = fixture::graph_and_queries();
+        let mut result = ZoneBuilder::new(&graph, &queries).build();
+
+        let key = ZoneKey::new(&[1, 2, 3, 4], &[Preference::Max; 4], 3);
+        for object_id in result.object_ids(&key) {
+            let edges = graph.map_edges();
+            let total: f32 = result
+                .zone_edges(object_id, &key)
+                .iter()
+                .map(|edge_id| edges[edge_id].len)
+                .sum();
+            let length = result.zone_length(object_id, &key);
+            assert!(length > 0.0 && length <= total + 1e-3);
+
+            for (edge_id, range) in result.zone(object_id, &key) {
+                assert_eq!(range.centroid_id, object_id);
+                if range.end > range.start {
+                    let middle = (range.start + range.end) / 2.0;
+                    let found = result.objects_at(*edge_id, middle, &key);
+                    assert!(found.contains(&object_id));
+                }
+            }
+        }
+
+        // overlapping ranges are counted once
+        let range = |start, end| Range {
+            start,
+            end,
+            centroid_id: 99,
+        };
+        let other = ZoneKey::new(&[1], &[Preference::Max], 1);
+        result.insert(&other, 1, vec![range(0.0, 4.0), range(2.0, 6.0)]);
+        result.insert(&other, 2, vec![range(1.0, 2.0)]);
+        assert_eq!(result.zone_length(99, &other), 7.0);
+        assert_eq!(result.zone_edges(99, &other), vec![1, 2]);
+
+        result.remove(99, &other);
+        assert!(result.zone(99, &other).is_empty());
+        assert!(result.objects_at(1, 3.0, &other).is_empty());
+
+        result.remove_all(2);
+        assert!(result.zone(2, &key).is_empty());
+        assert!(!result.object_ids(&key).contains(&2));
     }
 }
@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::prelude::*;
+
+/// First bytes of a binary snapshot
+const MAGIC: &[u8; 4] = b"TKDZ";
+
+/// Version written by this build, bump it when `Snapshot` changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// On-disk encoding of a `ResultVoronoi`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SnapshotFormat {
+    /// `MAGIC`, little endian version, then the bincode encoded snapshot
+    Binary,
+    /// JSON object with a top-level `version` field
+    Json,
+}
+
+/// Everything needed to rebuild a `ResultVoronoi` without the graph.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub version: u32,
+    pub edges: Vec<SnapshotEdge>,
+    pub zones: Vec<SnapshotZones>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SnapshotEdge {
+    pub id: EdgeId,
+    pub ni: NodeId,
+    pub nj: NodeId,
+    pub len: f32,
+}
+
+/// Zones of one `ZoneKey`
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SnapshotZones {
+    pub dimensions: Vec<DimensionIndex>,
+    pub preferences: Vec<Preference>,
+    pub k: K,
+    pub results: Vec<SnapshotEdgeResult>,
+}
+
+/// `EdgeResult` of one edge
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SnapshotEdgeResult {
+    pub edge_id: EdgeId,
+    pub edge_len: f32,
+    /// `(start, end, centroid_id)`
+    pub ranges: Vec<(f32, f32, CentroidId)>,
+    /// `(segment end, centroid ids)`
+    pub breakpoints: Vec<(f32, Vec<CentroidId>)>,
+}
+
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+}
+
+impl ResultVoronoi {
+    /// Save to `path`, see `write_to`.
+    pub fn save(&self, path: &Path, format: SnapshotFormat) -> Result<(), SnapshotError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer, format)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load from `path`, the format is detected from the content.
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_to<W: Write>(
+        &self,
+        mut writer: W,
+        format: SnapshotFormat,
+    ) -> Result<(), SnapshotError> {
+        let snapshot = self.to_snapshot();
+        match format {
+            SnapshotFormat::Binary => {
+                writer.write_all(MAGIC)?;
+                writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+                bincode::serialize_into(writer, &snapshot)?;
+            }
+            SnapshotFormat::Json => serde_json::to_writer(writer, &snapshot)?,
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, SnapshotError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let snapshot: Snapshot = if bytes.starts_with(MAGIC) {
+            let mut version = [0; 4];
+            version.copy_from_slice(bytes.get(4..8).ok_or(SnapshotError::Truncated)?);
+            check_version(u32::from_le_bytes(version))?;
+            bincode::deserialize(&bytes[8..])?
+        } else {
+            let header: SnapshotHeader = serde_json::from_slice(&bytes)?;
+            check_version(header.version)?;
+            serde_json::from_slice(&bytes)?
+        };
+
+        Self::from_snapshot(snapshot)
+    }
+}
+
+fn check_version(version: u32) -> Result<(), SnapshotError> {
+    if version == SNAPSHOT_VERSION {
+        Ok(())
+    } else {
+        Err(SnapshotError::UnsupportedVersion(version))
+    }
+}
+
+/// Error while saving or loading a snapshot
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Binary(bincode::Error),
+    Json(serde_json::Error),
+    /// Binary snapshot ends before its header
+    Truncated,
+    /// Snapshot written by an incompatible version
+    UnsupportedVersion(u32),
+    /// Zone on an edge missing from the snapshot edges
+    UnknownEdge(EdgeId),
+    /// Range not within its edge, reversed, or not a number
+    InvalidRange {
+        edge_id: EdgeId,
+        start: f32,
+        end: f32,
+    },
+    /// Segment end not within its edge, or no segment ending at node j
+    InvalidBreakpoint {
+        edge_id: EdgeId,
+        dist: f32,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{}", e),
+            SnapshotError::Binary(e) => write!(f, "invalid binary snapshot: {}", e),
+            SnapshotError::Json(e) => write!(f, "invalid JSON snapshot: {}", e),
+            SnapshotError::Truncated => write!(f, "truncated snapshot header"),
+            SnapshotError::UnsupportedVersion(v) => write!(
+                f,
+                "snapshot version {} is not supported, expected {}",
+                v, SNAPSHOT_VERSION
+            ),
+            SnapshotError::UnknownEdge(id) => write!(f, "zone on unknown edge {}", id),
+            SnapshotError::InvalidRange {
+                edge_id,
+                start,
+                end,
+            } => write!(f, "invalid range {} to {} on edge {}", start, end, edge_id),
+            SnapshotError::InvalidBreakpoint { edge_id, dist } => {
+                write!(f, "invalid segment end {} on edge {}", dist, edge_id)
+            }
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(e) => Some(e),
+            SnapshotError::Binary(e) => Some(e),
+            SnapshotError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(e: bincode::Error) -> Self {
+        SnapshotError::Binary(e)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(e: serde_json::Error) -> Self {
+        SnapshotError::Json(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ik::fixture;
+    use std::sync::Arc;
+
+    fn sample_result() -> ResultVoronoi {
+        let (graph, mut queries) = fixture::graph_and_queries();
+        // zones of another subspace with the same k
+        queries.insert(Arc::new(Query {
+            id: 10,
+            k: 3,
+            dimensions: vec![1, 2, 3],
+            preferences: vec![Preference::Max; 3],
+        }));
+        UpdateEngine::new(graph, queries).into_result()
+    }
+
+    fn all_locations(result: &ResultVoronoi) -> Vec<Vec<ObjectId>> {
+        let (graph, _) = fixture::graph_and_queries();
+        let mut locations = fixture::edge_locations(graph.map_edges().values());
+        locations.extend((1..=6).map(Location::Node));
+        result
+            .keys()
+            .iter()
+            .flat_map(|key| result.locate_all(&locations, key))
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let result = sample_result();
+        for format in [SnapshotFormat::Binary, SnapshotFormat::Json].iter() {
+            let mut bytes = Vec::new();
+            result.write_to(&mut bytes, *format).unwrap();
+            let loaded = ResultVoronoi::read_from(bytes.as_slice()).unwrap();
+            assert_eq!(loaded.keys(), result.keys());
+            assert_eq!(all_locations(&loaded), all_locations(&result));
+        }
+    }
+
+    #[test]
+    fn snapshot_unsupported_version() {
+        let mut bytes = Vec::new();
+        sample_result()
+            .write_to(&mut bytes, SnapshotFormat::Binary)
+            .unwrap();
+        bytes[4] = 99;
+        match ResultVoronoi::read_from(bytes.as_slice()) {
+            Err(SnapshotError::UnsupportedVersion(99)) => {}
+            r => panic!("unexpected {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn snapshot_unknown_edge() {
+        let mut snapshot = sample_result().to_snapshot();
+        snapshot.zones[0].results[0].edge_id = 99;
+        let bytes = serde_json::to_vec(&snapshot).unwrap();
+        match ResultVoronoi::read_from(bytes.as_slice()) {
+            Err(SnapshotError::UnknownEdge(99)) => {}
+            r => panic!("unexpected {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn snapshot_invalid_ranges() {
+        let snapshot = sample_result().to_snapshot();
+        let edge_id = snapshot.zones[0].results[0].edge_id;
+        let edge_len = snapshot.zones[0].results[0].edge_len;
+        for (start, end) in [(2.0, 1.0), (0.0, edge_len + 1.0), (f32::NAN, 1.0)].iter() {
+            let mut snapshot = sample_result().to_snapshot();
+            snapshot.zones[0].results[0].ranges[0].0 = *start;
+            snapshot.zones[0].results[0].ranges[0].1 = *end;
+            match ResultVoronoi::from_snapshot(snapshot) {
+                Err(SnapshotError::InvalidRange { edge_id: id, .. }) => assert_eq!(id, edge_id),
+                r => panic!("unexpected {:?}", r.map(|_| ())),
+            }
+        }
+
+        for dist in [-1.0, edge_len + 1.0].iter() {
+            let mut snapshot = sample_result().to_snapshot();
+            snapshot.zones[0].results[0].breakpoints[0].0 = *dist;
+            match ResultVoronoi::from_snapshot(snapshot) {
+                Err(SnapshotError::InvalidBreakpoint { edge_id: id, .. }) => {
+                    assert_eq!(id, edge_id)
+                }
+                r => panic!("unexpected {:?}", r.map(|_| ())),
+            }
+        }
+    }
+}
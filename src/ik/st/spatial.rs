@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Uniform grid over the edges of a network, to find the edge nearest to a
+/// coordinate. Edges are straight lines between their nodes and are kept in
+/// every cell their bounding box overlaps. Coordinates are projected on the
+/// plane around the center of the network, in the unit of the distance
+/// model, e.g. meters for WGS84 degrees.
+#[derive(Debug)]
+pub struct SpatialIndex {
+    /// Coordinate projected to `(0, 0)`
+    origin: (f32, f32),
+    /// Projected units per degree of longitude and of latitude
+    scale: (f32, f32),
+    min_x: f32,
+    min_y: f32,
+    cell_size: f32,
+    /// Cells of the bounding box of the network are `0..columns, 0..rows`
+    columns: i64,
+    rows: i64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    edges: Vec<IndexedEdge>,
+}
+
+#[derive(Debug)]
+struct IndexedEdge {
+    id: EdgeId,
+    len: f32,
+    from: (f32, f32),
+    to: (f32, f32),
+}
+
+/// Point of the network nearest to a coordinate
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Snap {
+    pub edge_id: EdgeId,
+    /// Offset from node i of the edge, in the unit of the edge length
+    pub offset: f32,
+    /// Offset from node i of the edge, as a fraction of the edge
+    pub ratio: f32,
+    /// Distance from the coordinate, in the unit of the distance model
+    pub distance: f32,
+}
+
+impl Snap {
+    pub fn location(&self) -> Location {
+        Location::Edge(self.edge_id, self.offset)
+    }
+}
+
+impl SpatialIndex {
+    /// Index `edges` of coordinates measured by `distance`, edges with a
+    /// node missing from `nodes` are left out.
+    pub fn new<'a, N, E>(nodes: N, edges: E, distance: DistanceModel) -> Self
+    where
+        N: IntoIterator<Item = &'a Arc<DataNode>>,
+        E: IntoIterator<Item = &'a Arc<DataEdge>>,
+    {
+        let nodes: HashMap<NodeId, &DataNode> =
+            nodes.into_iter().map(|n| (n.id, n.as_ref())).collect();
+        let mut edges: Vec<IndexedEdge> = edges
+            .into_iter()
+            .filter_map(|e| {
+                let ni = nodes.get(&e.ni)?;
+                let nj = nodes.get(&e.nj)?;
+                Some(IndexedEdge {
+                    id: e.id,
+                    len: e.len,
+                    from: (ni.lng, ni.lat),
+                    to: (nj.lng, nj.lat),
+                })
+            })
+            .collect();
+        edges.sort_by_key(|e| e.id);
+
+        let mut index = SpatialIndex {
+            origin: (0.0, 0.0),
+            scale: (1.0, 1.0),
+            min_x: 0.0,
+            min_y: 0.0,
+            cell_size: 1.0,
+            columns: 0,
+            rows: 0,
+            cells: HashMap::new(),
+            edges: Vec::new(),
+        };
+        if edges.is_empty() {
+            return index;
+        }
+
+        let (min_lng, min_lat, max_lng, max_lat) = Self::bounds(&edges);
+        index.origin = ((min_lng + max_lng) / 2.0, (min_lat + max_lat) / 2.0);
+        index.scale = distance.units_per_degree(index.origin.1);
+        for e in &mut edges {
+            e.from = index.project(e.from.0, e.from.1);
+            e.to = index.project(e.to.0, e.to.1);
+        }
+
+        // about one edge per cell
+        let (min_x, min_y, max_x, max_y) = Self::bounds(&edges);
+        let extent = (max_x - min_x).max(max_y - min_y);
+        if extent > 0.0 {
+            index.cell_size = extent / (edges.len() as f32).sqrt().ceil();
+        }
+        index.min_x = min_x;
+        index.min_y = min_y;
+        index.columns = index.cell(max_x, max_y).0 + 1;
+        index.rows = index.cell(max_x, max_y).1 + 1;
+
+        for (i, e) in edges.iter().enumerate() {
+            let (x0, y0) = index.cell(e.from.0.min(e.to.0), e.from.1.min(e.to.1));
+            let (x1, y1) = index.cell(e.from.0.max(e.to.0), e.from.1.max(e.to.1));
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    index.cells.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+        index.edges = edges;
+        index
+    }
+
+    pub fn from_graph(graph: &Graph) -> Self {
+        Self::new(
+            graph.map_nodes().values(),
+            graph.map_edges().values(),
+            graph.config.distance,
+        )
+    }
+
+    /// Smallest and greatest coordinates of `edges`, as
+    /// `(min_x, min_y, max_x, max_y)`.
+    fn bounds(edges: &[IndexedEdge]) -> (f32, f32, f32, f32) {
+        let (mut min_x, mut max_x) = (f32::MAX, f32::MIN);
+        let (mut min_y, mut max_y) = (f32::MAX, f32::MIN);
+        for e in edges {
+            for (x, y) in &[e.from, e.to] {
+                min_x = min_x.min(*x);
+                max_x = max_x.max(*x);
+                min_y = min_y.min(*y);
+                max_y = max_y.max(*y);
+            }
+        }
+        (min_x, min_y, max_x, max_y)
+    }
+
+    fn project(&self, lng: f32, lat: f32) -> (f32, f32) {
+        (
+            (lng - self.origin.0) * self.scale.0,
+            (lat - self.origin.1) * self.scale.1,
+        )
+    }
+
+    fn cell(&self, x: f32, y: f32) -> (i64, i64) {
+        (
+            ((x - self.min_x) / self.cell_size).floor() as i64,
+            ((y - self.min_y) / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// Snap a coordinate to the nearest point of the network, the edge with
+    /// the smallest id on ties. `None` if there are no edges.
+    pub fn snap(&self, lng: f32, lat: f32) -> Option<Snap> {
+        if self.edges.is_empty() {
+            return None;
+        }
+
+        let (x, y) = self.project(lng, lat);
+        let (cx, cy) = self.cell(x, y);
+        // rings nearer than the grid have no cells, start at the first one
+        // reaching it
+        let min_ring = [-cx, cx - (self.columns - 1), -cy, cy - (self.rows - 1), 0]
+            .iter()
+            .copied()
+            .max()?;
+        let max_ring = [cx, self.columns - 1 - cx, cy, self.rows - 1 - cy]
+            .iter()
+            .map(|d| d.abs())
+            .max()?;
+
+        let mut nearest: Option<Snap> = None;
+        for ring in min_ring..=max_ring {
+            for cell in self.ring(cx, cy, ring) {
+                for i in self.cells.get(&cell).into_iter().flatten() {
+                    let snap = self.edges[*i].project(x, y);
+                    let is_nearer = nearest
+                        .is_none_or(|n| (snap.distance, snap.edge_id) < (n.distance, n.edge_id));
+                    if is_nearer {
+                        nearest = Some(snap);
+                    }
+                }
+            }
+
+            // cells of the next rings are at least `ring` cells away
+            if nearest.is_some_and(|n| n.distance <= ring as f32 * self.cell_size) {
+                break;
+            }
+        }
+        nearest
+    }
+
+    /// Cells of the grid at Chebyshev distance `ring` from `(cx, cy)`,
+    /// which itself may be outside the grid.
+    fn ring(&self, cx: i64, cy: i64, ring: i64) -> Vec<(i64, i64)> {
+        let in_columns = |x: i64| (0..self.columns).contains(&x);
+        let in_rows = |y: i64| (0..self.rows).contains(&y);
+        if ring == 0 {
+            return if in_columns(cx) && in_rows(cy) {
+                vec![(cx, cy)]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let columns = (cx - ring).max(0)..=(cx + ring).min(self.columns - 1);
+        let rows = (cy - ring + 1).max(0)..=(cy + ring - 1).min(self.rows - 1);
+        let mut cells = Vec::new();
+        for y in [cy - ring, cy + ring]
+            .iter()
+            .copied()
+            .filter(|y| in_rows(*y))
+        {
+            cells.extend(columns.clone().map(|x| (x, y)));
+        }
+        for x in [cx - ring, cx + ring]
+            .iter()
+            .copied()
+            .filter(|x| in_columns(*x))
+        {
+            cells.extend(rows.clone().map(|y| (x, y)));
+        }
+        cells
+    }
+}
+
+impl IndexedEdge {
+    /// Nearest point of the edge to a projected coordinate.
+    fn project(&self, x: f32, y: f32) -> Snap {
+        let (dx, dy) = (self.to.0 - self.from.0, self.to.1 - self.from.1);
+        let len_sq = dx * dx + dy * dy;
+        let ratio = if len_sq > 0.0 {
+            (((x - self.from.0) * dx + (y - self.from.1) * dy) / len_sq).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let (px, py) = (self.from.0 + dx * ratio, self.from.1 + dy * ratio);
+        Snap {
+            edge_id: self.id,
+            offset: ratio * self.len,
+            ratio,
+            distance: ((x - px).powi(2) + (y - py).powi(2)).sqrt(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_objects() {
+        let conf = Arc::new(AppConfig::default());
+        let graph = Graph::new(conf).unwrap();
+        let index = SpatialIndex::from_graph(&graph);
+
+        for object in graph.all_objects() {
+            let edge = graph.edge_by_edge_id(object.edge_id);
+            let ni = graph.node_by_node_id(edge.ni);
+            let nj = graph.node_by_node_id(edge.nj);
+            let lng = ni.lng + (nj.lng - ni.lng) * object.dist;
+            let lat = ni.lat + (nj.lat - ni.lat) * object.dist;
+
+            // slightly off the edge
+            let snap = index.snap(lng + 0.01, lat - 0.01).unwrap();
+            assert_eq!(snap.edge_id, object.edge_id);
+            assert!((snap.offset - object.dist * edge.len).abs() < 0.1);
+            assert!(snap.distance < 0.02);
+        }
+
+        // far outside the network, snapped to its nearest corner
+        let snap = index.snap(1000.0, 1000.0).unwrap();
+        let edge = graph.edge_by_edge_id(snap.edge_id);
+        assert!(snap.offset == 0.0 || snap.offset == edge.len);
+
+        // rings between the coordinate and the network are skipped
+        let far = index.snap(1.0e5, -1.0e5).unwrap();
+        let edge = graph.edge_by_edge_id(far.edge_id);
+        assert!(far.offset == 0.0 || far.offset == edge.len);
+    }
+
+    #[test]
+    fn snap_haversine() {
+        let node = |id, lng, lat| Arc::new(DataNode { id, lng, lat });
+        let nodes = vec![
+            node(1, 10.0, 60.0),
+            node(2, 10.0, 60.01),
+            node(3, 10.004, 60.009),
+            node(4, 10.01, 60.009),
+        ];
+        let edges: Vec<Arc<DataEdge>> = [(1, 0, 1), (2, 2, 3)]
+            .iter()
+            .map(|(id, i, j)| {
+                let (ni, nj) = (nodes[*i].clone(), nodes[*j].clone());
+                Arc::new(DataEdge::with_distance(
+                    *id,
+                    ni,
+                    nj,
+                    DistanceModel::Haversine,
+                ))
+            })
+            .collect();
+
+        // fewer degrees away from edge 2, but a degree of longitude is half
+        // as long as one of latitude at 60 degrees north
+        let (lng, lat) = (10.006, 60.005);
+        let euclidean = SpatialIndex::new(&nodes, &edges, DistanceModel::Euclidean);
+        assert_eq!(euclidean.snap(lng, lat).unwrap().edge_id, 2);
+
+        let index = SpatialIndex::new(&nodes, &edges, DistanceModel::Haversine);
+        let snap = index.snap(lng, lat).unwrap();
+        assert_eq!(snap.edge_id, 1);
+        assert!((snap.ratio - 0.5).abs() < 1e-3);
+        assert!((snap.offset - edges[0].len / 2.0).abs() < 1.0);
+        let foot = node(5, 10.0, lat);
+        let distance = DistanceModel::Haversine.distance(&node(6, lng, lat), &foot);
+        assert!((snap.distance - distance).abs() < 1.0);
+    }
+
+    #[test]
+    fn ring_in_grid() {
+        let conf = Arc::new(AppConfig::default());
+        let graph = Graph::new(conf).unwrap();
+        let index = SpatialIndex::from_graph(&graph);
+        let in_grid =
+            |(x, y): &(i64, i64)| (0..index.columns).contains(x) && (0..index.rows).contains(y);
+
+        // only the cells of the grid, however far the ring reaches
+        let (cx, cy) = index.cell(1.0e7, -1.0e7);
+        let ring = cx.max(-cy);
+        let cells = index.ring(cx, cy, ring);
+        assert!(!cells.is_empty());
+        assert!(cells.iter().all(in_grid));
+        assert!(cells.len() as i64 <= 2 * (index.columns + index.rows));
+
+        let all: usize = (0..=index.columns.max(index.rows))
+            .map(|ring| index.ring(0, 0, ring).len())
+            .sum();
+        assert_eq!(all as i64, index.columns * index.rows);
+    }
+}
@@ -0,0 +1,337 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Summary of the zones of a `ResultVoronoi`, one `ZoneStats` per key.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub edges: usize,
+    /// Total length of the edges
+    pub network_length: f32,
+    pub zones: Vec<ZoneStats>,
+}
+
+/// Summary of the zones of one `ZoneKey`
+#[derive(Debug, Serialize)]
+pub struct ZoneStats {
+    #[serde(flatten)]
+    pub key: ZoneKey,
+    /// Objects having a zone
+    pub objects: usize,
+    /// Network length in the zone of at least one object
+    pub covered_length: f32,
+    /// `covered_length` as a fraction of the network length
+    pub coverage: f32,
+    /// Edges no zone reaches
+    pub uncovered_edges: usize,
+    /// Average number of zones over the covered length
+    pub mean_overlap: f32,
+    /// Network length in exactly as many zones as the index
+    pub overlap: Vec<f32>,
+    pub zone_length: LengthSummary,
+    /// Largest zones, largest first
+    pub largest: Vec<ZoneSize>,
+}
+
+/// Distribution of the zone lengths of the objects
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LengthSummary {
+    pub min: f32,
+    pub median: f32,
+    pub mean: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ZoneSize {
+    pub object_id: ObjectId,
+    pub length: f32,
+}
+
+impl ResultVoronoi {
+    /// Summary of the zones of every key, with the `top` largest zones
+    /// of each.
+    pub fn stats(&self, top: usize) -> Stats {
+        let mut edges: Vec<&Arc<DataEdge>> = self.edges().collect();
+        edges.sort_by_key(|e| e.id);
+        let network_length = edges.iter().map(|e| e.len).sum();
+        let zones = self
+            .keys()
+            .into_iter()
+            .map(|key| self.zone_stats(key, &edges, network_length, top))
+            .collect();
+        Stats {
+            edges: edges.len(),
+            network_length,
+            zones,
+        }
+    }
+
+    fn zone_stats(
+        &self,
+        key: ZoneKey,
+        edges: &[&Arc<DataEdge>],
+        network_length: f32,
+        top: usize,
+    ) -> ZoneStats {
+        let mut overlap: Vec<f32> = vec![0.0];
+        let mut covered_edges = HashSet::new();
+        for (edge, start, end, object_ids) in self.segments(&key) {
+            covered_edges.insert(edge.id);
+            if overlap.len() <= object_ids.len() {
+                overlap.resize(object_ids.len() + 1, 0.0);
+            }
+            overlap[object_ids.len()] += end - start;
+        }
+        let covered_length: f32 = overlap.iter().skip(1).sum();
+        overlap[0] = (network_length - covered_length).max(0.0);
+        let overlapping: f32 = overlap
+            .iter()
+            .enumerate()
+            .map(|(n, length)| n as f32 * length)
+            .sum();
+
+        let mut sizes: Vec<ZoneSize> = self
+            .object_ids(&key)
+            .into_iter()
+            .map(|object_id| ZoneSize {
+                object_id,
+                length: self.zone_length(object_id, &key),
+            })
+            .collect();
+        // largest first, smallest id on ties
+        sizes.sort_by(|a, b| {
+            b.length
+                .total_cmp(&a.length)
+                .then(a.object_id.cmp(&b.object_id))
+        });
+        let zone_length = LengthSummary::new(&sizes);
+
+        ZoneStats {
+            objects: sizes.len(),
+            covered_length,
+            coverage: ratio(covered_length, network_length),
+            uncovered_edges: edges
+                .iter()
+                .filter(|e| !covered_edges.contains(&e.id))
+                .count(),
+            mean_overlap: ratio(overlapping, covered_length),
+            overlap,
+            zone_length,
+            largest: sizes.into_iter().take(top).collect(),
+            key,
+        }
+    }
+}
+
+impl LengthSummary {
+    /// Summary of `sizes`, sorted from the largest.
+    fn new(sizes: &[ZoneSize]) -> Self {
+        if sizes.is_empty() {
+            return LengthSummary::default();
+        }
+        let n = sizes.len();
+        let median = if n % 2 == 1 {
+            sizes[n / 2].length
+        } else {
+            (sizes[n / 2 - 1].length + sizes[n / 2].length) / 2.0
+        };
+        LengthSummary {
+            min: sizes[n - 1].length,
+            median,
+            mean: sizes.iter().map(|s| s.length).sum::<f32>() / n as f32,
+            max: sizes[0].length,
+        }
+    }
+}
+
+/// `a / b`, 0 if `b` is 0.
+fn ratio(a: f32, b: f32) -> f32 {
+    if b > 0.0 {
+        a / b
+    } else {
+        0.0
+    }
+}
+
+impl Stats {
+    pub fn to_json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(self)
+    }
+}
+
+impl fmt::Display for Stats {
+    /// Table of the zones of every key, then the details of each.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "edges: {}, network length: {:.2}",
+            self.edges, self.network_length
+        )?;
+        let keys: Vec<String> = self.zones.iter().map(|z| z.key.to_string()).collect();
+        let width = keys.iter().map(|k| k.len()).max().unwrap_or(0).max(5);
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<width$}  {:>7}  {:>10}  {:>8}  {:>9}  {:>7}",
+            "zones",
+            "objects",
+            "covered",
+            "coverage",
+            "uncovered",
+            "overlap",
+            width = width
+        )?;
+        for (key, zone) in keys.iter().zip(&self.zones) {
+            writeln!(
+                f,
+                "{:<width$}  {:>7}  {:>10.2}  {:>7.1}%  {:>9}  {:>7.2}",
+                key,
+                zone.objects,
+                zone.covered_length,
+                zone.coverage * 100.0,
+                zone.uncovered_edges,
+                zone.mean_overlap,
+                width = width
+            )?;
+        }
+
+        for (key, zone) in keys.iter().zip(&self.zones) {
+            writeln!(f)?;
+            writeln!(f, "{}", key)?;
+            let overlap: Vec<String> = zone
+                .overlap
+                .iter()
+                .enumerate()
+                .map(|(n, length)| format!("{}: {:.2}", n, length))
+                .collect();
+            writeln!(f, "  length by overlap: {}", overlap.join(", "))?;
+            let LengthSummary {
+                min,
+                median,
+                mean,
+                max,
+            } = zone.zone_length;
+            writeln!(
+                f,
+                "  zone length: min {:.2}, median {:.2}, mean {:.2}, max {:.2}",
+                min, median, mean, max
+            )?;
+            let largest: Vec<String> = zone
+                .largest
+                .iter()
+                .map(|z| format!("{} ({:.2})", z.object_id, z.length))
+                .collect();
+            writeln!(f, "  largest: {}", largest.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn stats() {
+        let mut edges = HashMap::new();
+        for (id, len) in [(1, 10.0), (2, 5.0)] {
+            let edge = DataEdge {
+                id,
+                ni: id,
+                nj: id + 1,
+                len,
+                oneway: false,
+            };
+            edges.insert(id, Arc::new(edge));
+        }
+        let mut result = ResultVoronoi::from_edge_ids(edges);
+        let range = |start, end, centroid_id| Range {
+            start,
+            end,
+            centroid_id,
+        };
+        let key = ZoneKey::new(&[1, 2], &[Preference::Max; 2], 2);
+        result.insert(&key, 1, vec![range(0.0, 6.0, 1), range(4.0, 10.0, 2)]);
+        result.insert(&key, 1, vec![range(8.0, 9.0, 3)]);
+
+        let stats = result.stats(2);
+        assert_eq!((stats.edges, stats.network_length), (2, 15.0));
+        let zone = &stats.zones[0];
+        assert_eq!(zone.key, key);
+        assert_eq!(zone.objects, 3);
+        assert_eq!(zone.covered_length, 10.0);
+        assert_eq!(zone.uncovered_edges, 1);
+        assert_eq!(zone.overlap, vec![5.0, 7.0, 3.0]);
+        assert!((zone.mean_overlap - 1.3).abs() < 1e-6);
+        assert_eq!(
+            zone.zone_length,
+            LengthSummary {
+                min: 1.0,
+                median: 6.0,
+                mean: 13.0 / 3.0,
+                max: 6.0,
+            }
+        );
+        let largest: Vec<ObjectId> = zone.largest.iter().map(|z| z.object_id).collect();
+        assert_eq!(largest, vec![1, 2]);
+
+        let json = stats.to_json().unwrap();
+        assert_eq!(json["zones"][0]["k"], 2);
+        assert_eq!(json["zones"][0]["dimensions"], serde_json::json!([1, 2]));
+        assert_eq!(json["zones"][0]["largest"][0]["object_id"], 1);
+
+        let text = stats.to_string();
+        assert!(text.contains("k 2 of 1,2"));
+        assert!(text.contains("largest: 1 (6.00), 2 (6.00)"));
+    }
+
+    #[test]
+    fn stats_overlap() {
+        // zones of the same object meet on many segments of this network
+        let dir = tempfile::tempdir().unwrap();
+        Generator::new(0)
+            .network(Network::Planar)
+            .generate()
+            .write(dir.path())
+            .unwrap();
+        let conf = Arc::new(AppConfig {
+            max_dist: 50.0,
+            dataset_dir: dir.path().to_path_buf(),
+            paths: Paths::new(dir.path().to_path_buf()),
+            ..Default::default()
+        });
+        let reader = Reader::new(conf.clone());
+        let graph = Graph::new(conf).unwrap();
+        let queries = Queries::new(reader.read_query_csv().unwrap());
+        let result = ZoneBuilder::new(&graph, &queries).build();
+
+        let stats = result.stats(0);
+        assert!(stats.zones.iter().any(|zone| zone.overlap.len() > 2));
+        for zone in &stats.zones {
+            // length by the number of objects found in the middle of each
+            // segment
+            let segments = result.segments(&zone.key);
+            let locations: Vec<Location> = segments
+                .iter()
+                .map(|(edge, start, end, _)| Location::Edge(edge.id, (start + end) / 2.0))
+                .collect();
+            let found = result.locate_all(&locations, &zone.key);
+            let mut overlap = vec![0.0; zone.overlap.len()];
+            for ((_, start, end, _), object_ids) in segments.iter().zip(&found) {
+                assert!(!object_ids.is_empty());
+                overlap[object_ids.len()] += end - start;
+            }
+            overlap[0] = stats.network_length - overlap.iter().sum::<f32>();
+
+            for (n, (a, b)) in zone.overlap.iter().zip(&overlap).enumerate() {
+                assert!((a - b).abs() < 1e-2, "{}: {} in {} zones", zone.key, a, n);
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Read-only view of a `Graph` where some edges are split at virtual
+/// sources. The split edges live in the view only, so the same graph can be
+/// shared by many traversals at once.
+#[derive(Clone)]
+pub struct GraphView<'a> {
+    graph: &'a Graph,
+    edges: HashMap<GraphEdgeId, Edge>,
+    adjacency: HashMap<GraphNodeId, Vec<GraphEdgeId>>,
+    map_new_edge: HashMap<EdgeId, Vec<GraphEdgeId>>,
+    map_new_node: HashMap<EdgeId, Vec<GraphNodeId>>,
+    map_new_edge_len: HashMap<GraphEdgeId, f32>,
+}
+
+impl<'a> GraphView<'a> {
+    pub fn new(graph: &'a Graph) -> Self {
+        GraphView {
+            graph,
+            edges: HashMap::new(),
+            adjacency: HashMap::new(),
+            map_new_edge: HashMap::new(),
+            map_new_node: HashMap::new(),
+            map_new_edge_len: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &Arc<AppConfig> {
+        &self.graph.config
+    }
+
+    /// Split the edges of `objects` at the objects, each object becoming a
+    /// virtual source.
+    pub fn split_objects(&mut self, objects: &[Arc<DataObject>]) -> Vec<GraphNodeId> {
+        let mut map_edge_id: HashMap<EdgeId, Vec<(ObjectId, f32)>> = HashMap::new();
+        for o in objects {
+            let len = self.graph.edge_by_edge_id(o.edge_id).len;
+            map_edge_id
+                .entry(o.edge_id)
+                .or_default()
+                .push((o.id, o.dist * len));
+        }
+
+        let mut node_ids = Vec::new();
+        for (edge_id, sources) in map_edge_id {
+            self.split_edge(edge_id, sources);
+            node_ids.extend(self.map_new_node.get(&edge_id).unwrap());
+        }
+        node_ids
+    }
+
+    pub fn split_object_ids(&mut self, object_ids: &[ObjectId]) -> Vec<GraphNodeId> {
+        let objects: Vec<Arc<DataObject>> =
+            object_ids.iter().map(|id| self.graph.object(*id)).collect();
+        self.split_objects(&objects)
+    }
+
+    /// Split `edge_id` at `sources`, pairs of object id and offset from node
+    /// i of the edge. Splitting the same edge again replaces the previous
+    /// split.
+    pub fn split_edge(&mut self, edge_id: EdgeId, mut sources: Vec<(ObjectId, f32)>) {
+        self.unsplit_edge(edge_id);
+
+        let edge = self.graph.edge(GraphEdgeId::Original(edge_id)).unwrap();
+        sources.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let mut prev_node_id = edge.ni;
+        let mut prev_offset = 0.0;
+        let mut new_edge_ids = Vec::new();
+        let mut new_node_ids = Vec::new();
+
+        for (i, (object_id, offset)) in sources.iter().enumerate() {
+            let new_node_id = GraphNodeId::Object(*object_id);
+            new_node_ids.push(new_node_id);
+
+            // insert new edge before source
+            let prev_edge_id = GraphEdgeId::BeforeObject(*object_id);
+            self.add_edge(
+                edge,
+                prev_edge_id,
+                prev_node_id,
+                new_node_id,
+                prev_offset,
+                *offset,
+            );
+            new_edge_ids.push(prev_edge_id);
+
+            // insert new edge after the last source
+            if i == sources.len() - 1 {
+                let next_edge_id = GraphEdgeId::AfterObject(*object_id);
+                self.add_edge(edge, next_edge_id, new_node_id, edge.nj, *offset, edge.len);
+                new_edge_ids.push(next_edge_id);
+            }
+
+            prev_node_id = new_node_id;
+            prev_offset = *offset;
+        }
+
+        self.map_new_edge.insert(edge_id, new_edge_ids);
+        self.map_new_node.insert(edge_id, new_node_ids);
+    }
+
+    fn unsplit_edge(&mut self, edge_id: EdgeId) {
+        if let Some(node_ids) = self.map_new_node.remove(&edge_id) {
+            for node_id in node_ids {
+                for id in self.adjacency.remove(&node_id).unwrap_or_default() {
+                    if let Some(edge) = self.edges.remove(&id) {
+                        let n = edge.other(node_id);
+                        if let Some(edge_ids) = self.adjacency.get_mut(&n) {
+                            edge_ids.retain(|e| *e != id);
+                        }
+                    }
+                }
+            }
+        }
+        for new_edge_id in self.map_new_edge.remove(&edge_id).unwrap_or_default() {
+            self.map_new_edge_len.remove(&new_edge_id);
+        }
+    }
+
+    /// Objects of `edge` in `[start, end)`, with `dist` relative to the
+    /// new edge.
+    fn objects_in_between(edge: &Edge, start: f32, end: f32) -> Vec<Arc<DataObject>> {
+        edge.objects
+            .iter()
+            .filter_map(|o| {
+                let offset = o.dist * edge.len;
+                if offset >= start && offset < end {
+                    let mut o_new = o.clone();
+                    Arc::make_mut(&mut o_new).dist = (offset - start) / (end - start);
+                    Some(o_new)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Add the part `[start, end)` of the split `edge`.
+    fn add_edge(
+        &mut self,
+        edge: &Edge,
+        edge_id: GraphEdgeId,
+        prev_node_id: GraphNodeId,
+        node_id: GraphNodeId,
+        start: f32,
+        end: f32,
+    ) {
+        let mut new_edge = Edge::new(edge_id, end - start, prev_node_id, node_id);
+        new_edge.oneway = edge.oneway;
+        new_edge.add_objects(Self::objects_in_between(edge, start, end));
+        self.map_new_edge_len.insert(edge_id, new_edge.len);
+        self.edges.insert(edge_id, new_edge);
+        self.adjacency
+            .entry(prev_node_id)
+            .or_default()
+            .push(edge_id);
+        self.adjacency.entry(node_id).or_default().push(edge_id);
+    }
+
+    fn is_split(&self, edge: &Edge) -> bool {
+        match edge.id {
+            GraphEdgeId::Original(edge_id) => self.map_new_edge.contains_key(&edge_id),
+            _ => false,
+        }
+    }
+
+    /// Edges joining `n` with its neighbors, in either direction
+    pub fn edges(&self, n: GraphNodeId) -> Vec<&Edge> {
+        let mut edges: Vec<&Edge> = self.graph.edges(n).filter(|e| !self.is_split(e)).collect();
+        if let Some(edge_ids) = self.adjacency.get(&n) {
+            edges.extend(edge_ids.iter().map(|id| self.edges.get(id).unwrap()));
+        }
+        edges
+    }
+
+    /// Edges to leave `n` by, respecting one-way edges for the `direction`
+    /// of the config.
+    pub fn next_edges(&self, n: GraphNodeId) -> Vec<&Edge> {
+        let direction = self.config().direction;
+        self.edges(n)
+            .into_iter()
+            .filter(|e| e.allows(n, direction))
+            .collect()
+    }
+
+    pub fn neighbors(&self, n: GraphNodeId) -> Vec<GraphNodeId> {
+        self.edges(n).into_iter().map(|e| e.other(n)).collect()
+    }
+
+    pub fn edge(&self, edge_id: GraphEdgeId) -> Option<&Edge> {
+        self.edges
+            .get(&edge_id)
+            .or_else(|| self.graph.edge(edge_id).filter(|e| !self.is_split(e)))
+    }
+
+    /// Length of an edge added by splitting.
+    pub fn new_edge_len(&self, edge_id: GraphEdgeId) -> f32 {
+        *self.map_new_edge_len.get(&edge_id).unwrap()
+    }
+
+    pub fn map_new_edge(&self) -> HashMap<EdgeId, Vec<GraphEdgeId>> {
+        self.map_new_edge.clone()
+    }
+
+    pub fn map_new_node(&self) -> HashMap<EdgeId, Vec<GraphNodeId>> {
+        self.map_new_node.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_object(id: ObjectId, edge_id: EdgeId, dist: f32) -> Arc<DataObject> {
+        Arc::new(DataObject {
+            id,
+            edge_id,
+            dist,
+            attr: Vec::new(),
+            action: Action::Insertion,
+        })
+    }
+
+    #[test]
+    fn split_objects() {
+        let conf = Arc::new(AppConfig::default());
+        let graph = Graph::new(conf).unwrap();
+        let edge_count = graph.all_edge_ids().len();
+
+        let objects = vec![
+            new_object(100, 3, 0.3),
+            new_object(101, 3, 0.4),
+            new_object(102, 3, 0.8),
+        ];
+        let mut view = GraphView::new(&graph);
+        let new_node_ids = view.split_objects(&objects);
+        assert_eq!(new_node_ids.len(), 3);
+
+        let new_edge_ids = view.map_new_edge().remove(&3).unwrap();
+        assert_eq!(
+            new_edge_ids,
+            vec![
+                GraphEdgeId::BeforeObject(100),
+                GraphEdgeId::BeforeObject(101),
+                GraphEdgeId::BeforeObject(102),
+                GraphEdgeId::AfterObject(102),
+            ]
+        );
+
+        let data_edge = graph.edge_by_edge_id(3);
+        let ni = GraphNodeId::Original(data_edge.ni);
+        let nj = GraphNodeId::Original(data_edge.nj);
+        assert!(view.edge(GraphEdgeId::Original(3)).is_none());
+        assert!(!view.neighbors(ni).contains(&nj));
+        assert!(view.neighbors(ni).contains(&GraphNodeId::Object(100)));
+
+        let edge = view.edge(GraphEdgeId::BeforeObject(102)).unwrap();
+        assert_eq!(
+            (edge.ni, edge.nj),
+            (GraphNodeId::Object(101), GraphNodeId::Object(102))
+        );
+        assert!((edge.len - 0.4 * data_edge.len).abs() < 1e-3);
+        assert_eq!(view.new_edge_len(GraphEdgeId::BeforeObject(102)), edge.len);
+
+        // the graph itself is left untouched
+        assert!(graph.edge(GraphEdgeId::Original(3)).is_some());
+        assert_eq!(graph.all_edge_ids().len(), edge_count);
+    }
+
+    #[test]
+    fn split_edge_again() {
+        let conf = Arc::new(AppConfig::default());
+        let graph = Graph::new(conf).unwrap();
+        let mut view = GraphView::new(&graph);
+        view.split_objects(&[new_object(100, 3, 0.3), new_object(101, 3, 0.6)]);
+        view.split_objects(&[new_object(102, 3, 0.5)]);
+
+        assert!(view.neighbors(GraphNodeId::Object(100)).is_empty());
+        assert_eq!(view.neighbors(GraphNodeId::Object(102)).len(), 2);
+        assert_eq!(view.map_new_node().get(&3).unwrap().len(), 1);
+        assert_eq!(view.map_new_edge_len.len(), 2);
+    }
+}
@@ -1,69 +1,484 @@
-mod config;
-mod ik;
-mod prelude;
-mod queries;
-mod src;
-mod types;
-
-use prelude::*;
+use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
+use structopt::StructOpt;
+use t::*;
 
-fn main() {
-    let conf: AppConfig = Default::default();
-
-    println!("{}", "main()");
-    println!("{:?}", conf);
+#[derive(StructOpt, Debug)]
+#[structopt(name = "t", about = "k-dominant zones of objects on a road network")]
+enum Command {
+    /// Compute zones of all objects of a dataset
+    Build {
+        #[structopt(flatten)]
+        dataset: DatasetOpt,
+        /// Number of threads, 0 uses one per CPU
+        #[structopt(long, default_value = "0")]
+        threads: usize,
+        /// Save the result as a snapshot, JSON if the path ends with `.json`
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Look up objects whose zone covers a location of a saved result
+    Query {
+        /// Snapshot written by `build` or `update`
+        #[structopt(short, long, parse(from_os_str))]
+        snapshot: PathBuf,
+        #[structopt(short)]
+        k: K,
+        /// Dimensions of the zones as in the query file, e.g. `1,2:min,4`,
+        /// needed if zones of several subspaces have k
+        #[structopt(long)]
+        dimensions: Option<String>,
+        /// Edge of the location
+        #[structopt(
+            long,
+            required_unless_one = &["node", "lng"],
+            conflicts_with_all = &["node", "lng"]
+        )]
+        edge: Option<EdgeId>,
+        /// Offset from node i of `--edge`
+        #[structopt(long, default_value = "0")]
+        offset: f32,
+        /// Node of the location
+        #[structopt(long, conflicts_with = "lng")]
+        node: Option<NodeId>,
+        /// Longitude of the location, snapped to the nearest edge
+        #[structopt(long, requires_all = &["lat", "nodes"], allow_hyphen_values = true)]
+        lng: Option<f32>,
+        /// Latitude of the location
+        #[structopt(long, requires = "lng", allow_hyphen_values = true)]
+        lat: Option<f32>,
+        /// Node file of the dataset, to snap `--lng` and `--lat`
+        #[structopt(long, parse(from_os_str))]
+        nodes: Option<PathBuf>,
+        /// Distance model of the dataset, to snap `--lng` and `--lat`
+        #[structopt(long, default_value = "euclidean")]
+        distance: DistanceModel,
+    },
+    /// Compute zones, then apply insertion and deletion events
+    Update {
+        #[structopt(flatten)]
+        dataset: DatasetOpt,
+        /// Events in the object format, applied in order
+        #[structopt(short, long, parse(from_os_str))]
+        events: PathBuf,
+        /// Save the result as a snapshot, JSON if the path ends with `.json`
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Write a synthetic dataset, with events.txt if events are asked for
+    Generate {
+        /// Directory to write node.txt, edge.txt, object.txt and query.txt to
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+        #[structopt(long, default_value = "1")]
+        seed: u64,
+        /// `grid` or `planar`
+        #[structopt(long, default_value = "grid")]
+        network: Network,
+        /// Nodes per row and per column
+        #[structopt(long, default_value = "10")]
+        size: u32,
+        /// Distance between neighboring nodes
+        #[structopt(long, default_value = "10")]
+        spacing: f32,
+        /// `ind`, `cor` or `anticor`
+        #[structopt(long, default_value = "ind")]
+        distribution: Distribution,
+        #[structopt(long, default_value = "4")]
+        max_dim: DimensionIndex,
+        #[structopt(long, default_value = "20")]
+        objects: u32,
+        /// Insertion events of new objects
+        #[structopt(long, default_value = "0")]
+        insertions: u32,
+        /// Deletion events of existing objects
+        #[structopt(long, default_value = "0")]
+        deletions: u32,
+    },
+    /// Convert a road network of another format to node.txt and edge.txt,
+    /// edge lengths in a `length` column to read with `--weight length`
+    Import {
+        /// `cal` for cal.cnode.txt and cal.cedge.txt, or `dimacs` for .co
+        /// and .gr files
+        #[structopt(long)]
+        format: Format,
+        /// File with the nodes, cal.cnode.txt or .co
+        #[structopt(parse(from_os_str))]
+        nodes: PathBuf,
+        /// File with the edges, cal.cedge.txt or .gr
+        #[structopt(parse(from_os_str))]
+        edges: PathBuf,
+        /// Scale coordinates to 0..100, edge lengths follow the scaled
+        /// coordinates instead of the files
+        #[structopt(long)]
+        normalize: bool,
+        /// Directory to write node.txt and edge.txt to
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Write zones of a saved result as GeoJSON
+    Export {
+        /// Snapshot written by `build` or `update`
+        #[structopt(short, long, parse(from_os_str))]
+        snapshot: PathBuf,
+        /// Node file of the dataset of the snapshot
+        #[structopt(long, parse(from_os_str))]
+        nodes: PathBuf,
+        #[structopt(short)]
+        k: K,
+        /// Dimensions of the zones as in the query file, e.g. `1,2:min,4`,
+        /// needed if zones of several subspaces have k
+        #[structopt(long)]
+        dimensions: Option<String>,
+        /// Objects whose zones are written, all objects if not given
+        #[structopt(long)]
+        objects: Vec<ObjectId>,
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Summary of a saved result
+    Stats {
+        /// Snapshot written by `build` or `update`
+        #[structopt(short, long, parse(from_os_str))]
+        snapshot: PathBuf,
+        /// Largest zones listed per key
+        #[structopt(long, default_value = "10")]
+        top: usize,
+        /// Print JSON instead of a table
+        #[structopt(long)]
+        json: bool,
+    },
+}
 
-    construct_new_query();
+#[derive(StructOpt, Debug)]
+struct DatasetOpt {
+    /// TOML config file, the other flags override its values
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Directory with node.txt, edge.txt, object.txt and query.txt, needed
+    /// unless `--config` gives it
+    #[structopt(short, long, parse(from_os_str), required_unless = "config")]
+    dataset: Option<PathBuf>,
+    /// Object file, instead of object.txt of the dataset
+    #[structopt(long, parse(from_os_str))]
+    objects: Option<PathBuf>,
+    /// Query file, instead of query.txt of the dataset
+    #[structopt(long, parse(from_os_str))]
+    queries: Option<PathBuf>,
+    #[structopt(long)]
+    max_dim: Option<DimensionIndex>,
+    #[structopt(long)]
+    max_dist: Option<f32>,
+    /// `euclidean` on normalized coordinates, or `haversine` meters on
+    /// WGS84 degrees
+    #[structopt(long)]
+    distance: Option<DistanceModel>,
+    /// Weight column of the edge file used as edge length, e.g. `length`
+    /// for imported networks. Edges are as long as the distance between
+    /// their nodes if not given
+    #[structopt(long)]
+    weight: Option<String>,
+    /// Zones along one-way edges by distance `from_object` or `to_object`
+    #[structopt(long)]
+    direction: Option<Direction>,
+    /// Smallest k, queries over all dimensions are generated instead of
+    /// read when a k bound is given
+    #[structopt(long)]
+    k_min: Option<K>,
+    /// Greatest k, defaults to `max_dim`
+    #[structopt(long)]
+    k_max: Option<K>,
 }
 
-fn construct_new_query() {
-    let mut conf = AppConfig::default();
-    conf.max_dim = 10;
-    conf.max_dist = 100.0;
-    conf.path(String::from("dataset/california/normalized"));
-    conf.object_path(String::from("dataset/objects/ind.txt"));
-    let conf = Arc::new(conf);
-    let reader = Reader::new(conf.clone());
-    let mut graph = Graph::new(conf.clone());
-    let mut result = ResultVoronoi::from_edge_ids(graph.map_edges());
+impl DatasetOpt {
+    fn config(&self) -> Result<AppConfig, ConfigError> {
+        let mut conf = match &self.config {
+            Some(path) => AppConfig::from_file(path)?,
+            // the dataset paths are replaced by `--dataset`, required then
+            None => AppConfig::default(),
+        };
+        if let Some(dataset) = &self.dataset {
+            conf.dataset_dir = dataset.clone();
+            conf.paths = Paths::new(dataset.clone());
+        }
+        if let Some(objects) = &self.objects {
+            conf.paths.object_path = objects.clone();
+        }
+        if let Some(queries) = &self.queries {
+            conf.paths.query_path = queries.clone();
+        }
+        if let Some(max_dim) = self.max_dim {
+            conf.max_dim = max_dim;
+        }
+        if let Some(max_dist) = self.max_dist {
+            conf.max_dist = max_dist;
+        }
+        if let Some(distance) = self.distance {
+            conf.distance = distance;
+        }
+        if let Some(weight) = &self.weight {
+            conf.weight = Some(weight.clone());
+        }
+        if let Some(direction) = self.direction {
+            conf.direction = direction;
+        }
+        conf.validate()?;
+        Ok(conf)
+    }
 
-    let mut objects = graph.all_objects();
-    objects.sort_by(|a, b| a.id.cmp(&b.id));
+    fn queries(&self, conf: &Arc<AppConfig>) -> Result<Queries, ReaderError> {
+        if self.k_min.is_none() && self.k_max.is_none() {
+            let reader = Reader::new(conf.clone());
+            return Ok(Queries::new(reader.read_query_csv()?));
+        }
 
-    let ks = generate_k(conf.max_dim);
+        let k_min = self.k_min.unwrap_or(conf.max_dim / 2 + 1);
+        let k_max = self.k_max.unwrap_or(conf.max_dim);
+        let dimensions: Vec<DimensionIndex> = (1..=conf.max_dim).collect();
+        let preferences = Reader::new(conf.clone()).preferences()?;
+        let queries = (k_min..=k_max)
+            .enumerate()
+            .map(|(i, k)| {
+                Arc::new(Query {
+                    id: i as QueryId + 1,
+                    k,
+                    dimensions: dimensions.clone(),
+                    preferences: preferences.clone(),
+                })
+            })
+            .collect();
+        Ok(Queries::new(queries))
+    }
+}
 
-    for object in objects {
-        println!("{}", object.id);
-        if object.id == 30 {
-            break;
-        }
+fn main() {
+    if let Err(e) = run(Command::from_args()) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
 
-        let mut voronoi: Voronoi;
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Build {
+            dataset,
+            threads,
+            output,
+        } => {
+            let conf = Arc::new(dataset.config()?);
+            let graph = Graph::new(conf.clone())?;
+            let queries = dataset.queries(&conf)?;
 
-        let mut ks0 = ks.clone();
-        let first_k = ks0.remove(0);
-        voronoi = Voronoi::initial_voronoi(&mut graph, object.id, first_k);
-        voronoi.save_to_result(&mut result, first_k);
-        print!("k {}", first_k);
+            let builder = ZoneBuilder::new(&graph, &queries).threads(threads)?;
+            let start = Instant::now();
+            let result = builder.build();
+            println!(
+                "zones of {} objects in {:?}",
+                graph.all_objects().len(),
+                start.elapsed()
+            );
+            save(&result, output.as_deref())?;
+        }
+        Command::Query {
+            snapshot,
+            k,
+            dimensions,
+            edge,
+            offset,
+            node,
+            lng,
+            lat,
+            nodes,
+            distance,
+        } => {
+            let result = ResultVoronoi::load(&snapshot)?;
+            let location = match (edge, node, lng.zip(lat), nodes) {
+                (Some(edge_id), ..) => Location::Edge(edge_id, offset),
+                (None, Some(node_id), ..) => Location::Node(node_id),
+                (None, None, Some((lng, lat)), Some(nodes)) => {
+                    let mut conf = AppConfig::default();
+                    conf.paths.node_path = nodes;
+                    let nodes = Reader::new(Arc::new(conf)).read_node_csv()?;
+                    let snap = SpatialIndex::new(&nodes, result.edges(), distance)
+                        .snap(lng, lat)
+                        .ok_or("no edge to snap to")?;
+                    println!(
+                        "edge {} offset {} ({} away)",
+                        snap.edge_id, snap.offset, snap.distance
+                    );
+                    snap.location()
+                }
+                _ => unreachable!(),
+            };
+            let key = zone_key(&result, k, dimensions.as_deref())?;
+            for object_id in result.locate(location, &key) {
+                println!("{}", object_id);
+            }
+        }
+        Command::Update {
+            dataset,
+            events,
+            output,
+        } => {
+            let conf = Arc::new(dataset.config()?);
+            let graph = Graph::new(conf.clone())?;
+            let queries = dataset.queries(&conf)?;
+            let events = graph.read_objects(&events)?;
 
-        for k in &ks0 {
-            print!(" {}", *k);
-            voronoi.continue_voronoi(*k);
-            voronoi.save_to_result(&mut result, *k);
+            let mut engine = UpdateEngine::new(graph, queries);
+            let start = Instant::now();
+            let count = events.len();
+            engine.apply_all(events)?;
+            println!("{} events in {:?}", count, start.elapsed());
+            save(engine.result(), output.as_deref())?;
+        }
+        Command::Generate {
+            output,
+            seed,
+            network,
+            size,
+            spacing,
+            distribution,
+            max_dim,
+            objects,
+            insertions,
+            deletions,
+        } => {
+            let dataset = Generator::new(seed)
+                .network(network)
+                .size(size, size)
+                .spacing(spacing)
+                .distribution(distribution)
+                .dimensions(max_dim)
+                .objects(objects)
+                .events(insertions, deletions)
+                .generate();
+            dataset.write(&output)?;
+            println!(
+                "{} nodes, {} edges, {} objects, {} events",
+                dataset.nodes.len(),
+                dataset.edges.len(),
+                dataset.objects.len(),
+                dataset.events.len()
+            );
+        }
+        Command::Import {
+            format,
+            nodes,
+            edges,
+            normalize,
+            output,
+        } => {
+            let network = Importer::new(format)
+                .normalize(normalize)
+                .import(&nodes, &edges)?;
+            network.write(&output)?;
+            println!(
+                "{} nodes, {} edges",
+                network.nodes.len(),
+                network.edges.len()
+            );
+        }
+        Command::Export {
+            snapshot,
+            nodes,
+            k,
+            dimensions,
+            objects,
+            output,
+        } => {
+            let result = ResultVoronoi::load(&snapshot)?;
+            let mut conf = AppConfig::default();
+            conf.paths.node_path = nodes;
+            let nodes = Reader::new(Arc::new(conf)).read_node_csv()?;
+            let object_ids = if objects.is_empty() {
+                None
+            } else {
+                Some(objects.as_slice())
+            };
+            let key = zone_key(&result, k, dimensions.as_deref())?;
+            result.save_geojson(&output, &nodes, &key, object_ids)?;
+        }
+        Command::Stats {
+            snapshot,
+            top,
+            json,
+        } => {
+            let stats = ResultVoronoi::load(&snapshot)?.stats(top);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print!("{}", stats);
+            }
         }
-        println!(" ");
-        graph.clean();
     }
+    Ok(())
 }
 
-fn generate_k(k_max: K) -> Vec<K> {
-    let mut ks = Vec::new();
+/// Key of the zones of `result` for `k`, over `dimensions` written as in
+/// the query file if given. A dimension without preference matches both.
+fn zone_key(
+    result: &ResultVoronoi,
+    k: K,
+    dimensions: Option<&str>,
+) -> Result<ZoneKey, Box<dyn Error>> {
+    let criteria = match dimensions {
+        Some(dimensions) => {
+            let mut criteria = Vec::new();
+            for criterion in dimensions.split(',') {
+                let (d, preference) = match criterion.split_once(':') {
+                    Some((d, p)) => (d, Some(p)),
+                    None => (criterion, None),
+                };
+                let d = d.trim().parse::<DimensionIndex>()?;
+                let preference = match preference {
+                    Some(p) => Some(
+                        p.parse::<Preference>()
+                            .map_err(|_| format!("invalid preference {}", p))?,
+                    ),
+                    None => None,
+                };
+                criteria.push((d, preference));
+            }
+            criteria.sort();
+            Some(criteria)
+        }
+        None => None,
+    };
 
-    let lower_bound = (k_max / 2) + 1;
-    for i in lower_bound..(k_max + 1) {
-        ks.push(i);
+    let mut keys: Vec<ZoneKey> = result
+        .keys()
+        .into_iter()
+        .filter(|key| key.k == k)
+        .filter(|key| {
+            criteria.as_ref().is_none_or(|criteria| {
+                criteria.len() == key.dimensions.len()
+                    && criteria
+                        .iter()
+                        .zip(key.dimensions.iter().zip(&key.preferences))
+                        .all(|((d, p), (key_d, key_p))| d == key_d && p.is_none_or(|p| p == *key_p))
+            })
+        })
+        .collect();
+    match keys.len() {
+        0 => Err(format!("no zones for k {}", k).into()),
+        1 => Ok(keys.remove(0)),
+        _ => {
+            let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+            Err(format!("zones of {} match, pass --dimensions", keys.join(" and ")).into())
+        }
     }
+}
 
-    ks
+fn save(result: &ResultVoronoi, path: Option<&Path>) -> Result<(), SnapshotError> {
+    if let Some(path) = path {
+        let format = match path.extension() {
+            Some(ext) if ext == "json" => SnapshotFormat::Json,
+            _ => SnapshotFormat::Binary,
+        };
+        result.save(path, format)?;
+    }
+    Ok(())
 }
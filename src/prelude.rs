@@ -1,6 +1,9 @@
 pub use crate::config::*;
 pub use crate::ik::st::graph::Graph;
 pub use crate::queries::Queries;
+pub use crate::src::error::ReaderError;
+pub use crate::src::generator::{Dataset, Distribution, Generator, Network};
+pub use crate::src::import::{Format, Importer, RoadNetwork};
 pub use crate::src::reader::Reader;
 pub use crate::src::types::*;
 pub use crate::types::*;
@@ -1,5 +1,4 @@
 use crate::prelude::*;
-use std::collections::HashMap;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -9,25 +8,52 @@ pub struct Queries {
 
 impl Queries {
     pub fn new(queries: Vec<Arc<Query>>) -> Self {
-        let mut groups: HashMap<Vec<DimensionIndex>, Group> = HashMap::new();
+        let mut itself = Queries { inner: Vec::new() };
         for q in queries {
-            if let Some(group) = groups.get_mut(&q.dimensions) {
-                group.queries.push(q);
-            } else {
-                let dim = q.dimensions.clone();
-                let new_group = Group {
-                    dimensions: q.dimensions.clone(),
-                    queries: vec![q],
-                };
-                groups.insert(dim, new_group);
-            }
+            itself.insert(q);
         }
-        let mut inner = Vec::new();
-        for (_, group) in &mut groups {
-            group.queries.sort_by(|a, b| a.k.partial_cmp(&b.k).unwrap());
-            inner.push(group.clone());
+        itself
+    }
+
+    /// Register `query` in the group of its dimensions and preferences,
+    /// queries share a group only if they agree on preferences too.
+    pub fn insert(&mut self, query: Arc<Query>) {
+        let group = self
+            .inner
+            .iter_mut()
+            .find(|g| g.dimensions == query.dimensions && g.preferences == query.preferences);
+        if let Some(group) = group {
+            // after queries of the same k
+            let index = group.queries.partition_point(|q| q.k <= query.k);
+            group.queries.insert(index, query);
+        } else {
+            self.inner.push(Group {
+                dimensions: query.dimensions.clone(),
+                preferences: query.preferences.clone(),
+                queries: vec![query],
+            });
         }
-        Queries { inner }
+    }
+
+    /// Unregister query `query_id`, dropping its group once empty.
+    pub fn remove(&mut self, query_id: QueryId) -> Option<Arc<Query>> {
+        for (i, group) in self.inner.iter_mut().enumerate() {
+            if let Some(index) = group.queries.iter().position(|q| q.id == query_id) {
+                let query = group.queries.remove(index);
+                if group.queries.is_empty() {
+                    self.inner.remove(i);
+                }
+                return Some(query);
+            }
+        }
+        None
+    }
+
+    pub fn query(&self, query_id: QueryId) -> Option<&Arc<Query>> {
+        self.inner
+            .iter()
+            .flat_map(|g| g.queries.iter())
+            .find(|q| q.id == query_id)
     }
 
     #[cfg(test)]
@@ -35,7 +61,7 @@ impl Queries {
         self.inner.len()
     }
 
-    pub fn iter(&self) -> QueryIterator {
+    pub fn iter(&self) -> QueryIterator<'_> {
         QueryIterator::new(&self.inner)
     }
 }
@@ -47,10 +73,7 @@ pub struct QueryIterator<'a> {
 
 impl<'a> QueryIterator<'a> {
     fn new(queries: &'a Vec<Group>) -> Self {
-        QueryIterator {
-            queries: queries,
-            index: 0,
-        }
+        QueryIterator { queries, index: 0 }
     }
 }
 
@@ -67,16 +90,32 @@ impl<'a> Iterator for QueryIterator<'a> {
 #[derive(Clone, Debug)]
 pub struct Group {
     dimensions: Vec<DimensionIndex>,
+    preferences: Vec<Preference>,
     queries: Vec<Arc<Query>>,
 }
 
 impl Group {
-    pub fn iter(&self) -> GroupIterator {
+    /// Dimensions shared by all queries of this group
+    pub fn dimensions(&self) -> &[DimensionIndex] {
+        &self.dimensions
+    }
+
+    /// Preference of each of `dimensions`
+    pub fn preferences(&self) -> &[Preference] {
+        &self.preferences
+    }
+
+    pub fn iter(&self) -> GroupIterator<'_> {
         GroupIterator::new(&self.queries)
     }
 
+    /// Key of the zones of the queries of this group with `k`
+    pub fn zone_key(&self, k: K) -> ZoneKey {
+        ZoneKey::new(&self.dimensions, &self.preferences, k)
+    }
+
     pub fn pop_first(&mut self) -> Option<Arc<Query>> {
-        if self.queries.len() != 0 {
+        if !self.queries.is_empty() {
             Some(self.queries.remove(0))
         } else {
             None
@@ -86,6 +125,10 @@ impl Group {
     pub fn remove_less_k(&mut self, k: K) {
         self.queries.retain(|q| q.k >= k);
     }
+
+    pub fn remove_greater_k(&mut self, k: K) {
+        self.queries.retain(|q| q.k <= k);
+    }
 }
 
 pub struct GroupIterator<'a> {
@@ -122,7 +165,7 @@ mod tests {
         let conf: AppConfig = Default::default();
         let conf = Arc::new(conf);
         let reader = Reader::new(conf);
-        let queries = reader.read_query_csv();
+        let queries = reader.read_query_csv().unwrap();
 
         let queries = Queries::new(queries);
         assert_eq!(queries.length(), 1);
@@ -134,6 +177,50 @@ mod tests {
                 is_exists = true;
             }
         }
-        assert_eq!(is_exists, true);
+        assert!(is_exists);
+    }
+
+    #[test]
+    fn queries_group_by_preferences() {
+        let query = |id, preferences| {
+            Arc::new(Query {
+                id,
+                k: 2,
+                dimensions: vec![1, 2],
+                preferences,
+            })
+        };
+        let queries = Queries::new(vec![
+            query(1, vec![Preference::Max, Preference::Max]),
+            query(2, vec![Preference::Min, Preference::Max]),
+            query(3, vec![Preference::Max, Preference::Max]),
+        ]);
+        assert_eq!(queries.length(), 2);
+    }
+
+    #[test]
+    fn queries_insert_remove() {
+        let query = |id, k, dimensions: Vec<DimensionIndex>| {
+            let preferences = vec![Preference::Max; dimensions.len()];
+            Arc::new(Query {
+                id,
+                k,
+                dimensions,
+                preferences,
+            })
+        };
+        let mut queries =
+            Queries::new(vec![query(1, 3, vec![1, 2, 3]), query(2, 1, vec![1, 2, 3])]);
+        queries.insert(query(3, 2, vec![1, 2, 3]));
+        queries.insert(query(4, 2, vec![2, 4]));
+        assert_eq!(queries.length(), 2);
+        let ks: Vec<K> = queries.iter().next().unwrap().iter().map(|q| q.k).collect();
+        assert_eq!(ks, vec![1, 2, 3]);
+
+        assert_eq!(queries.remove(4).unwrap().id, 4);
+        assert_eq!(queries.length(), 1);
+        assert!(queries.remove(4).is_none());
+        assert!(queries.query(4).is_none());
+        assert_eq!(queries.query(3).unwrap().k, 2);
     }
 }
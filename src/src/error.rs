@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::prelude::*;
+
+/// Error while reading a dataset file. Lines and columns are 1-based.
+#[derive(Debug)]
+pub enum ReaderError {
+    /// File cannot be opened or a row cannot be read
+    Csv { path: PathBuf, source: csv::Error },
+    /// Row has no value in `column`
+    MissingField {
+        path: PathBuf,
+        line: u64,
+        column: usize,
+    },
+    /// Value in `column` cannot be parsed or is out of range
+    InvalidValue {
+        path: PathBuf,
+        line: u64,
+        column: usize,
+        value: String,
+    },
+    /// Edge refers to a node which is not in the node file
+    UnknownNode {
+        path: PathBuf,
+        line: u64,
+        column: usize,
+        node_id: NodeId,
+    },
+    /// Header has no column of the given name
+    UnknownColumn { path: PathBuf, name: String },
+    /// Object lies on an edge which is not in the edge file
+    UnknownEdge {
+        path: PathBuf,
+        object_id: ObjectId,
+        edge_id: EdgeId,
+    },
+    /// Edge joins the same two nodes as an earlier edge, other than a
+    /// one-way edge opposite to a one-way edge
+    DuplicateEdge {
+        path: PathBuf,
+        line: u64,
+        edge_id: EdgeId,
+        other_id: EdgeId,
+    },
+    /// Query of the query file is not valid, see `UpdateEngine::add_query`
+    InvalidQuery {
+        path: PathBuf,
+        line: u64,
+        source: UpdateError,
+    },
+}
+
+impl fmt::Display for ReaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReaderError::Csv { path, source } => write!(f, "{}: {}", path.display(), source),
+            ReaderError::MissingField { path, line, column } => write!(
+                f,
+                "{}:{}: column {}: missing value",
+                path.display(),
+                line,
+                column
+            ),
+            ReaderError::InvalidValue {
+                path,
+                line,
+                column,
+                value,
+            } => write!(
+                f,
+                "{}:{}: column {}: invalid value {:?}",
+                path.display(),
+                line,
+                column,
+                value
+            ),
+            ReaderError::UnknownNode {
+                path,
+                line,
+                column,
+                node_id,
+            } => write!(
+                f,
+                "{}:{}: column {}: unknown node {}",
+                path.display(),
+                line,
+                column,
+                node_id
+            ),
+            ReaderError::UnknownColumn { path, name } => {
+                write!(f, "{}: no column {:?}", path.display(), name)
+            }
+            ReaderError::UnknownEdge {
+                path,
+                object_id,
+                edge_id,
+            } => write!(
+                f,
+                "{}: object {} lies on unknown edge {}",
+                path.display(),
+                object_id,
+                edge_id
+            ),
+            ReaderError::DuplicateEdge {
+                path,
+                line,
+                edge_id,
+                other_id,
+            } => write!(
+                f,
+                "{}:{}: edge {} joins the same nodes as edge {}",
+                path.display(),
+                line,
+                edge_id,
+                other_id
+            ),
+            ReaderError::InvalidQuery { path, line, source } => {
+                write!(f, "{}:{}: {}", path.display(), line, source)
+            }
+        }
+    }
+}
+
+impl Error for ReaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReaderError::Csv { source, .. } => Some(source),
+            ReaderError::InvalidQuery { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
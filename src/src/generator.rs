@@ -0,0 +1,446 @@
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Distribution of object attributes, as in `script/generate_objects.py`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    /// Each attribute uniform in `[0, 1)`
+    Independent,
+    /// Attributes within 20% of a common base value
+    Correlated,
+    /// Attributes of an object sum up to 1
+    AntiCorrelated,
+}
+
+impl FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ind" => Ok(Distribution::Independent),
+            "cor" => Ok(Distribution::Correlated),
+            "anticor" => Ok(Distribution::AntiCorrelated),
+            _ => Err(format!(
+                "unknown distribution {}, expected ind, cor or anticor",
+                s
+            )),
+        }
+    }
+}
+
+/// Shape of the generated road network
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Network {
+    /// Regular grid, each node linked to its right and lower neighbor
+    Grid,
+    /// Grid with moved nodes, some diagonals and some edges removed. Edges
+    /// never cross and the network stays connected.
+    Planar,
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(Network::Grid),
+            "planar" => Ok(Network::Planar),
+            _ => Err(format!("unknown network {}, expected grid or planar", s)),
+        }
+    }
+}
+
+/// Generate reproducible synthetic datasets. The same seed and settings
+/// always give the same dataset.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    seed: u64,
+    network: Network,
+    width: u32,
+    height: u32,
+    spacing: f32,
+    distribution: Distribution,
+    max_dim: DimensionIndex,
+    object_count: u32,
+    insertion_count: u32,
+    deletion_count: u32,
+}
+
+/// Generated dataset, written to files by `Dataset::write`
+#[derive(Debug)]
+pub struct Dataset {
+    pub max_dim: DimensionIndex,
+    pub nodes: Vec<Arc<DataNode>>,
+    pub edges: Vec<Arc<DataEdge>>,
+    pub objects: Vec<Arc<DataObject>>,
+    /// Insertions and deletions applied after `objects`, in order
+    pub events: Vec<Arc<DataObject>>,
+    pub queries: Vec<Arc<Query>>,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Generator {
+            seed,
+            network: Network::Grid,
+            width: 10,
+            height: 10,
+            spacing: 10.0,
+            distribution: Distribution::Independent,
+            max_dim: 4,
+            object_count: 20,
+            insertion_count: 0,
+            deletion_count: 0,
+        }
+    }
+
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Number of nodes per row and per column.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width.max(2);
+        self.height = height.max(2);
+        self
+    }
+
+    /// Distance between neighboring nodes of the grid.
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn distribution(mut self, distribution: Distribution) -> Self {
+        self.distribution = distribution;
+        self
+    }
+
+    pub fn dimensions(mut self, max_dim: DimensionIndex) -> Self {
+        self.max_dim = max_dim;
+        self
+    }
+
+    pub fn objects(mut self, count: u32) -> Self {
+        self.object_count = count;
+        self
+    }
+
+    /// Number of insertion and deletion events, shuffled together.
+    pub fn events(mut self, insertions: u32, deletions: u32) -> Self {
+        self.insertion_count = insertions;
+        self.deletion_count = deletions;
+        self
+    }
+
+    pub fn generate(&self) -> Dataset {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let nodes = self.nodes(&mut rng);
+        let edges = self.edges(&mut rng, &nodes);
+
+        let objects: Vec<Arc<DataObject>> = (1..=self.object_count)
+            .map(|id| self.object(&mut rng, id, edges.len() as EdgeId))
+            .collect();
+        let events = self.events_of(&mut rng, &objects, edges.len() as EdgeId);
+
+        Dataset {
+            max_dim: self.max_dim,
+            nodes,
+            edges,
+            objects,
+            events,
+            queries: self.queries(),
+        }
+    }
+
+    fn nodes(&self, rng: &mut ChaCha8Rng) -> Vec<Arc<DataNode>> {
+        let mut nodes = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (mut lng, mut lat) = (x as f32 * self.spacing, y as f32 * self.spacing);
+                if self.network == Network::Planar {
+                    // small enough for every cell to stay convex, so edges
+                    // and diagonals cannot cross
+                    lng += rng.gen_range(-0.2..0.2) * self.spacing;
+                    lat += rng.gen_range(-0.2..0.2) * self.spacing;
+                }
+                nodes.push(Arc::new(DataNode {
+                    id: y * self.width + x + 1,
+                    lng,
+                    lat,
+                }));
+            }
+        }
+        nodes
+    }
+
+    fn edges(&self, rng: &mut ChaCha8Rng, nodes: &[Arc<DataNode>]) -> Vec<Arc<DataEdge>> {
+        let id = |x: u32, y: u32| (y * self.width + x) as usize;
+        let mut pairs = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if x + 1 < self.width {
+                    pairs.push((id(x, y), id(x + 1, y)));
+                }
+                if y + 1 < self.height {
+                    pairs.push((id(x, y), id(x, y + 1)));
+                }
+            }
+        }
+
+        if self.network == Network::Planar {
+            // at most one diagonal per cell
+            for y in 0..self.height - 1 {
+                for x in 0..self.width - 1 {
+                    if rng.gen_bool(0.3) {
+                        if rng.gen_bool(0.5) {
+                            pairs.push((id(x, y), id(x + 1, y + 1)));
+                        } else {
+                            pairs.push((id(x + 1, y), id(x, y + 1)));
+                        }
+                    }
+                }
+            }
+            pairs = Self::thin_out(rng, pairs, nodes.len());
+        }
+
+        pairs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (a, b))| {
+                Arc::new(DataEdge::new(
+                    i as EdgeId + 1,
+                    nodes[a].clone(),
+                    nodes[b].clone(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Keep a random spanning tree of `pairs`, plus some of the other pairs.
+    fn thin_out(
+        rng: &mut ChaCha8Rng,
+        mut pairs: Vec<(usize, usize)>,
+        node_count: usize,
+    ) -> Vec<(usize, usize)> {
+        pairs.shuffle(rng);
+        let mut parent: Vec<usize> = (0..node_count).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let mut kept = Vec::new();
+        for (a, b) in pairs {
+            let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+            if ra != rb {
+                parent[ra] = rb;
+                kept.push((a, b));
+            } else if rng.gen_bool(0.6) {
+                kept.push((a, b));
+            }
+        }
+        kept.sort_unstable();
+        kept
+    }
+
+    fn object(&self, rng: &mut ChaCha8Rng, id: ObjectId, edge_count: EdgeId) -> Arc<DataObject> {
+        Arc::new(DataObject {
+            id,
+            edge_id: rng.gen_range(1..=edge_count),
+            dist: rng.gen(),
+            attr: self.attributes(rng),
+            action: Action::Insertion,
+        })
+    }
+
+    fn attributes(&self, rng: &mut ChaCha8Rng) -> Vec<f32> {
+        let n = self.max_dim as usize;
+        match self.distribution {
+            Distribution::Independent => (0..n).map(|_| rng.gen()).collect(),
+            Distribution::Correlated => {
+                let base: f32 = rng.gen();
+                (0..n)
+                    .map(|_| {
+                        let lower = base - base * rng.gen_range(0.0..0.2);
+                        let upper = base + base * rng.gen_range(0.0..0.2);
+                        if lower < upper {
+                            rng.gen_range(lower..upper)
+                        } else {
+                            base
+                        }
+                    })
+                    .collect()
+            }
+            Distribution::AntiCorrelated => {
+                let values: Vec<f32> = (0..n).map(|_| rng.gen()).collect();
+                let sum: f32 = values.iter().sum();
+                values.iter().map(|v| v / sum).collect()
+            }
+        }
+    }
+
+    /// Insertions of new objects and deletions of live ones, shuffled.
+    fn events_of(
+        &self,
+        rng: &mut ChaCha8Rng,
+        objects: &[Arc<DataObject>],
+        edge_count: EdgeId,
+    ) -> Vec<Arc<DataObject>> {
+        let mut is_insertion = vec![true; self.insertion_count as usize];
+        is_insertion.extend(vec![false; self.deletion_count as usize]);
+        is_insertion.shuffle(rng);
+
+        let mut live = objects.to_vec();
+        let mut next_id = self.object_count + 1;
+        let mut events = Vec::new();
+        for insertion in is_insertion {
+            if insertion {
+                let object = self.object(rng, next_id, edge_count);
+                next_id += 1;
+                live.push(object.clone());
+                events.push(object);
+            } else if !live.is_empty() {
+                let object = live.swap_remove(rng.gen_range(0..live.len()));
+                let mut deletion = (*object).clone();
+                deletion.action = Action::Deletion;
+                events.push(Arc::new(deletion));
+            }
+        }
+        events
+    }
+
+    /// Queries over all dimensions, from the smallest meaningful k to
+    /// `max_dim`.
+    fn queries(&self) -> Vec<Arc<Query>> {
+        let dimensions: Vec<DimensionIndex> = (1..=self.max_dim).collect();
+        (self.max_dim / 2 + 1..=self.max_dim)
+            .enumerate()
+            .map(|(i, k)| {
+                Arc::new(Query {
+                    id: i as QueryId + 1,
+                    k,
+                    dimensions: dimensions.clone(),
+                    preferences: vec![Preference::Max; dimensions.len()],
+                })
+            })
+            .collect()
+    }
+}
+
+impl Dataset {
+    /// Write node.txt, edge.txt, object.txt, query.txt and, if there are
+    /// events, events.txt to `dir`, in the format `Reader` expects.
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        let mut nodes = String::from("id lng lat\n");
+        for n in &self.nodes {
+            writeln!(nodes, "{} {} {}", n.id, n.lng, n.lat).unwrap();
+        }
+        fs::write(dir.join("node.txt"), nodes)?;
+
+        let mut edges = String::from("id node_i node_j\n");
+        for e in &self.edges {
+            writeln!(edges, "{} {} {}", e.id, e.ni, e.nj).unwrap();
+        }
+        fs::write(dir.join("edge.txt"), edges)?;
+
+        fs::write(dir.join("object.txt"), self.objects_csv(&self.objects))?;
+        if !self.events.is_empty() {
+            fs::write(dir.join("events.txt"), self.objects_csv(&self.events))?;
+        }
+
+        let mut queries = String::from("k");
+        for d in 1..=self.max_dim {
+            write!(queries, " d{}", d).unwrap();
+        }
+        queries.push('\n');
+        for q in &self.queries {
+            write!(queries, "{}", q.k).unwrap();
+            for d in &q.dimensions {
+                write!(queries, " {}", d).unwrap();
+            }
+            queries.push('\n');
+        }
+        fs::write(dir.join("query.txt"), queries)
+    }
+
+    fn objects_csv(&self, objects: &[Arc<DataObject>]) -> String {
+        let mut csv = String::from("action id edge_id distance");
+        for d in 1..=self.max_dim {
+            write!(csv, " d{}", d).unwrap();
+        }
+        csv.push('\n');
+        for o in objects {
+            let action = match o.action {
+                Action::Insertion => 1,
+                Action::Deletion => 0,
+            };
+            write!(csv, "{} {} {} {}", action, o.id, o.edge_id, o.dist).unwrap();
+            for a in &o.attr {
+                write!(csv, " {}", a).unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_and_read() {
+        let generator = Generator::new(7)
+            .network(Network::Planar)
+            .size(5, 4)
+            .distribution(Distribution::AntiCorrelated)
+            .dimensions(3)
+            .objects(15)
+            .events(4, 3);
+        let dataset = generator.generate();
+        assert_eq!(dataset.nodes.len(), 20);
+        assert_eq!(dataset.objects.len(), 15);
+        assert_eq!(dataset.events.len(), 7);
+        for o in &dataset.objects {
+            assert!((o.attr.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        }
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        dataset.write(dir).unwrap();
+        let conf = AppConfig {
+            max_dim: 3,
+            dataset_dir: dir.to_path_buf(),
+            paths: Paths::new(dir.to_path_buf()),
+            ..Default::default()
+        };
+        conf.validate().unwrap();
+        let reader = Reader::new(Arc::new(conf.clone()));
+        assert_eq!(reader.read_query_csv().unwrap().len(), 2);
+        let events = reader.read_object_csv_at(&dir.join("events.txt")).unwrap();
+        assert_eq!(events.len(), 7);
+
+        let graph = Graph::new(Arc::new(conf)).unwrap();
+        assert_eq!(graph.all_objects().len(), 15);
+
+        // same seed, same dataset
+        let again = generator.generate();
+        assert_eq!(format!("{:?}", again), format!("{:?}", dataset));
+    }
+}
@@ -0,0 +1,458 @@
+use csv::{ReaderBuilder, StringRecord};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// Coordinates of a normalized network are within `[0, NORMALIZED_MAX]`
+const NORMALIZED_MAX: f32 = 100.0;
+
+/// DIMACS coordinates are degrees times `DIMACS_SCALE`
+const DIMACS_SCALE: f64 = 1_000_000.0;
+
+/// Road network format read by `Importer`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `cal.cnode.txt` with `id lng lat` and `cal.cedge.txt` with
+    /// `id node_i node_j length`, 0-based and without header
+    California,
+    /// DIMACS shortest path challenge, `.co` with `v id x y` and `.gr` with
+    /// `a from to weight` lines
+    Dimacs,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cal" => Ok(Format::California),
+            "dimacs" => Ok(Format::Dimacs),
+            _ => Err(format!("unknown format {}, expected cal or dimacs", s)),
+        }
+    }
+}
+
+/// Read road networks in formats other than the one of `Reader`. Edge
+/// lengths are taken from the files unless the network is normalized.
+#[derive(Clone, Debug)]
+pub struct Importer {
+    format: Format,
+    normalize: bool,
+}
+
+/// Imported road network, written to files by `RoadNetwork::write`
+#[derive(Debug)]
+pub struct RoadNetwork {
+    pub nodes: Vec<Arc<DataNode>>,
+    pub edges: Vec<Arc<DataEdge>>,
+}
+
+impl Importer {
+    pub fn new(format: Format) -> Self {
+        Importer {
+            format,
+            normalize: false,
+        }
+    }
+
+    /// Scale both coordinates to `[0, 100]`, as `script/normalizer.py`
+    /// does, with edge lengths measured on the scaled coordinates. Like the
+    /// script, longitudes are scaled by absolute value, which mirrors a
+    /// network west of Greenwich.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Read nodes from `node_path` and edges from `edge_path`.
+    pub fn import(&self, node_path: &Path, edge_path: &Path) -> Result<RoadNetwork, ReaderError> {
+        let (mut nodes, mut edges) = match self.format {
+            Format::California => {
+                let nodes = Self::read_california_nodes(node_path)?;
+                let edges = Self::read_california_edges(edge_path, &nodes)?;
+                (nodes, edges)
+            }
+            Format::Dimacs => {
+                let nodes = Self::read_dimacs_nodes(node_path)?;
+                let edges = Self::read_dimacs_edges(edge_path, &nodes)?;
+                (nodes, edges)
+            }
+        };
+
+        if self.normalize {
+            nodes = Self::normalized(&nodes);
+            let map_nodes: HashMap<NodeId, Arc<DataNode>> =
+                nodes.iter().map(|n| (n.id, n.clone())).collect();
+            for edge in &mut edges {
+                let ni = map_nodes[&edge.ni].clone();
+                let nj = map_nodes[&edge.nj].clone();
+                edge.len = DistanceModel::Euclidean.distance(&ni, &nj);
+            }
+        }
+
+        Ok(RoadNetwork {
+            nodes,
+            edges: edges.into_iter().map(Arc::new).collect(),
+        })
+    }
+
+    /// Ids are shifted by one, as by `script/normalizer.py`, so the object
+    /// files of the normalized California network apply.
+    fn read_california_nodes(path: &Path) -> Result<Vec<Arc<DataNode>>, ReaderError> {
+        let mut vec = Vec::new();
+        for record in Self::records(path)? {
+            let record = record?;
+            let id = Reader::parse::<NodeId>(path, &record, 0)?;
+            let lng = Reader::parse::<f32>(path, &record, 1)?;
+            let lat = Reader::parse::<f32>(path, &record, 2)?;
+            vec.push(Arc::new(DataNode {
+                id: id + 1,
+                lng,
+                lat,
+            }));
+        }
+        vec.sort_by_key(|n| n.id);
+        Ok(vec)
+    }
+
+    fn read_california_edges(
+        path: &Path,
+        nodes: &[Arc<DataNode>],
+    ) -> Result<Vec<DataEdge>, ReaderError> {
+        let mut vec = Vec::new();
+        for record in Self::records(path)? {
+            let record = record?;
+            let id = Reader::parse::<EdgeId>(path, &record, 0)?;
+            let ni = Self::node(path, &record, 1, nodes, 1)?;
+            let nj = Self::node(path, &record, 2, nodes, 1)?;
+            let len = Reader::parse::<f32>(path, &record, 3)?;
+            vec.push(DataEdge {
+                id: id + 1,
+                ni,
+                nj,
+                len,
+                oneway: false,
+            });
+        }
+        vec.sort_by_key(|e| e.id);
+        Ok(vec)
+    }
+
+    /// Coordinates in degrees, from `v` lines.
+    fn read_dimacs_nodes(path: &Path) -> Result<Vec<Arc<DataNode>>, ReaderError> {
+        let mut vec = Vec::new();
+        for record in Self::records(path)? {
+            let record = record?;
+            if record.get(0) != Some("v") {
+                continue;
+            }
+            let id = Reader::parse::<NodeId>(path, &record, 1)?;
+            let lng = (Reader::parse::<f64>(path, &record, 2)? / DIMACS_SCALE) as f32;
+            let lat = (Reader::parse::<f64>(path, &record, 3)? / DIMACS_SCALE) as f32;
+            vec.push(Arc::new(DataNode { id, lng, lat }));
+        }
+        vec.sort_by_key(|n| n.id);
+        Ok(vec)
+    }
+
+    /// Edges from `a` lines, numbered from 1 in order of appearance. Arcs
+    /// of the same weight in both directions between two nodes become one
+    /// two-way edge, other arcs become one-way edges, so arcs of different
+    /// weights back and forth are a pair of opposite one-way edges. Of
+    /// parallel arcs the lightest is kept, and self-loops are skipped as no
+    /// shortest path takes them.
+    fn read_dimacs_edges(
+        path: &Path,
+        nodes: &[Arc<DataNode>],
+    ) -> Result<Vec<DataEdge>, ReaderError> {
+        // lightest arc of each direction, in order of appearance
+        let mut arcs: Vec<DataEdge> = Vec::new();
+        let mut map_arc_index: HashMap<(NodeId, NodeId), usize> = HashMap::new();
+        for record in Self::records(path)? {
+            let record = record?;
+            if record.get(0) != Some("a") {
+                continue;
+            }
+            let ni = Self::node(path, &record, 1, nodes, 0)?;
+            let nj = Self::node(path, &record, 2, nodes, 0)?;
+            let len = Reader::parse::<f32>(path, &record, 3)?;
+            if ni == nj {
+                continue;
+            }
+
+            match map_arc_index.get(&(ni, nj)) {
+                Some(&i) => arcs[i].len = arcs[i].len.min(len),
+                None => {
+                    map_arc_index.insert((ni, nj), arcs.len());
+                    arcs.push(DataEdge {
+                        id: 0,
+                        ni,
+                        nj,
+                        len,
+                        oneway: true,
+                    });
+                }
+            }
+        }
+
+        let mut vec: Vec<DataEdge> = Vec::new();
+        let mut is_merged = vec![false; arcs.len()];
+        for i in 0..arcs.len() {
+            if is_merged[i] {
+                continue;
+            }
+            let arc = &arcs[i];
+            let mut edge = DataEdge {
+                id: vec.len() as EdgeId + 1,
+                ni: arc.ni,
+                nj: arc.nj,
+                len: arc.len,
+                oneway: true,
+            };
+            if let Some(&j) = map_arc_index.get(&(arc.nj, arc.ni)) {
+                if arcs[j].len == arc.len {
+                    edge.oneway = false;
+                    is_merged[j] = true;
+                }
+            }
+            vec.push(edge);
+        }
+        Ok(vec)
+    }
+
+    /// Rows of a space separated file without header, where the number of
+    /// fields may differ between rows.
+    fn records(
+        path: &Path,
+    ) -> Result<impl Iterator<Item = Result<StringRecord, ReaderError>> + '_, ReaderError> {
+        let reader = ReaderBuilder::new()
+            .delimiter(b' ')
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)
+            .map_err(|source| ReaderError::Csv {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(reader
+            .into_records()
+            .map(move |result| Reader::record(path, result)))
+    }
+
+    /// Node id in the `index`-th field of `record`, plus `shift`, which
+    /// must be one of `nodes`.
+    fn node(
+        path: &Path,
+        record: &StringRecord,
+        index: usize,
+        nodes: &[Arc<DataNode>],
+        shift: NodeId,
+    ) -> Result<NodeId, ReaderError> {
+        let node_id = Reader::parse::<NodeId>(path, record, index)? + shift;
+        match nodes.binary_search_by_key(&node_id, |n| n.id) {
+            Ok(_) => Ok(node_id),
+            Err(_) => Err(ReaderError::UnknownNode {
+                path: path.to_path_buf(),
+                line: record.position().map(|p| p.line()).unwrap_or(0),
+                column: index + 1,
+                node_id,
+            }),
+        }
+    }
+
+    fn normalized(nodes: &[Arc<DataNode>]) -> Vec<Arc<DataNode>> {
+        let (mut min_lng, mut max_lng) = (f32::MAX, f32::MIN);
+        let (mut min_lat, mut max_lat) = (f32::MAX, f32::MIN);
+        for n in nodes {
+            min_lng = min_lng.min(n.lng.abs());
+            max_lng = max_lng.max(n.lng.abs());
+            min_lat = min_lat.min(n.lat);
+            max_lat = max_lat.max(n.lat);
+        }
+
+        // a network on a single line keeps its other coordinate at 0
+        let scale = |value: f32, min: f32, max: f32| {
+            if max > min {
+                (value - min) / (max - min) * NORMALIZED_MAX
+            } else {
+                0.0
+            }
+        };
+        nodes
+            .iter()
+            .map(|n| {
+                Arc::new(DataNode {
+                    id: n.id,
+                    lng: scale(n.lng.abs(), min_lng, max_lng),
+                    lat: scale(n.lat, min_lat, max_lat),
+                })
+            })
+            .collect()
+    }
+}
+
+impl RoadNetwork {
+    /// Write node.txt and edge.txt to `dir`, in the format `Reader` expects.
+    /// Edge lengths are kept in a `length` column, read with `length` as
+    /// `AppConfig::weight`.
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        let mut nodes = String::from("id lng lat\n");
+        for n in &self.nodes {
+            writeln!(nodes, "{} {} {}", n.id, n.lng, n.lat).unwrap();
+        }
+        fs::write(dir.join("node.txt"), nodes)?;
+
+        let has_oneway = self.edges.iter().any(|e| e.oneway);
+        let mut edges = String::from("id node_i node_j length");
+        if has_oneway {
+            edges.push_str(" oneway");
+        }
+        edges.push('\n');
+        for e in &self.edges {
+            write!(edges, "{} {} {} {}", e.id, e.ni, e.nj, e.len).unwrap();
+            if has_oneway {
+                write!(edges, " {}", e.oneway as u8).unwrap();
+            }
+            edges.push('\n');
+        }
+        fs::write(dir.join("edge.txt"), edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn dataset_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("dataset/california")
+    }
+
+    #[test]
+    fn import_california() {
+        let original = dataset_dir().join("original");
+        let network = Importer::new(Format::California)
+            .normalize(true)
+            .import(
+                &original.join("cal.cnode.txt"),
+                &original.join("cal.cedge.txt"),
+            )
+            .unwrap();
+
+        // same network as converted by `script/normalizer.py`
+        let mut conf = AppConfig::default();
+        conf.path(String::from("dataset/california/normalized"));
+        let reader = Reader::new(Arc::new(conf));
+        let nodes = reader.read_node_csv().unwrap();
+        let edges = reader.read_edge_csv(&nodes).unwrap();
+        assert_eq!(network.nodes.len(), nodes.len());
+        assert_eq!(network.edges.len(), edges.len());
+        for (a, b) in network.nodes.iter().zip(&nodes) {
+            assert_eq!(a.id, b.id);
+            assert!((a.lng - b.lng).abs() < 1e-3);
+            assert!((a.lat - b.lat).abs() < 1e-3);
+        }
+        for (a, b) in network.edges.iter().zip(&edges) {
+            assert_eq!((a.id, a.ni, a.nj), (b.id, b.ni, b.nj));
+            assert!((a.len - b.len).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn import_dimacs() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(
+            dir.join("test.co"),
+            "c coordinates\np aux sp co 3\nv 1 -73530767 41085396\nv 2 -73530538 41086098\nv 3 -73519366 41048796\n",
+        )
+        .unwrap();
+        // the lighter of the arcs from 1 to 2 weighs as the arc back, and
+        // the self-loop at 3 is skipped
+        fs::write(
+            dir.join("test.gr"),
+            "c arcs\np sp 3 5\na 1 2 803\na 2 1 790\na 1 2 790\na 2 3 158\na 3 3 12\n",
+        )
+        .unwrap();
+
+        let network = Importer::new(Format::Dimacs)
+            .import(&dir.join("test.co"), &dir.join("test.gr"))
+            .unwrap();
+        assert_eq!(network.nodes.len(), 3);
+        assert!((network.nodes[0].lng + 73.53077).abs() < 1e-4);
+        assert_eq!(network.edges.len(), 2);
+        assert_eq!(
+            (network.edges[0].len, network.edges[0].oneway),
+            (790.0, false)
+        );
+        assert_eq!((network.edges[1].ni, network.edges[1].nj), (2, 3));
+        assert!(network.edges[1].oneway);
+
+        // written network reads back the same
+        network.write(dir).unwrap();
+        let conf = AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            weight: Some(String::from("length")),
+            ..Default::default()
+        };
+        let reader = Reader::new(Arc::new(conf));
+        let nodes = reader.read_node_csv().unwrap();
+        let edges = reader.read_edge_csv(&nodes).unwrap();
+        assert_eq!(edges[0].len, 790.0);
+        assert!(edges[1].oneway);
+    }
+
+    #[test]
+    fn import_dimacs_asymmetric() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(
+            dir.join("test.co"),
+            "p aux sp co 3\nv 1 -73530767 41085396\nv 2 -73530538 41086098\nv 3 -73519366 41048796\n",
+        )
+        .unwrap();
+        // 1 and 2 are joined both ways by arcs of different weights, 2 and
+        // 3 by an arc from 3 only
+        fs::write(
+            dir.join("test.gr"),
+            "p sp 3 3\na 1 2 803\na 3 2 158\na 2 1 790\n",
+        )
+        .unwrap();
+
+        let network = Importer::new(Format::Dimacs)
+            .import(&dir.join("test.co"), &dir.join("test.gr"))
+            .unwrap();
+        let edges: Vec<(EdgeId, NodeId, NodeId, f32, bool)> = network
+            .edges
+            .iter()
+            .map(|e| (e.id, e.ni, e.nj, e.len, e.oneway))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (1, 1, 2, 803.0, true),
+                (2, 3, 2, 158.0, true),
+                (3, 2, 1, 790.0, true),
+            ]
+        );
+
+        // the opposite one-way edges read back as a pair
+        network.write(dir).unwrap();
+        let conf = AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            weight: Some(String::from("length")),
+            ..Default::default()
+        };
+        let graph = Graph::new_empty_object(Arc::new(conf)).unwrap();
+        assert_eq!(graph.edges(GraphNodeId::Original(1)).count(), 2);
+        assert_eq!(graph.edge_by_edge_id(3).len, 790.0);
+    }
+}
@@ -1,2 +1,5 @@
+pub mod error;
+pub mod generator;
+pub mod import;
 pub mod reader;
 pub mod types;
@@ -1,8 +1,14 @@
-use csv::ReaderBuilder;
+use csv::{ReaderBuilder, StringRecord};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::prelude::*;
 
+/// Column of the edge file marking one-way edges
+const ONEWAY: &str = "oneway";
+
 /// Centralized reader of all datasets
 pub struct Reader {
     config: Arc<AppConfig>,
@@ -15,24 +21,69 @@ impl Reader {
     }
 
     /// Read object from CSV file
-    pub fn read_object_csv(&self) -> Vec<Arc<DataObject>> {
-        let config = self.config.clone();
+    pub fn read_object_csv(&self) -> Result<Vec<Arc<DataObject>>, ReaderError> {
+        self.read_object_csv_at(self.config.paths.object_path.as_path())
+    }
+
+    /// Read objects, or insertion/deletion events, from any file in the
+    /// object format.
+    pub fn read_object_csv_at(&self, path: &Path) -> Result<Vec<Arc<DataObject>>, ReaderError> {
+        self.read_objects(path, |record| {
+            let edge_id = Self::parse::<EdgeId>(path, record, 2)?;
+            let dist = Self::parse::<f32>(path, record, 3)?;
+            Ok((edge_id, dist))
+        })
+    }
+
+    /// Read a file in the object format whose objects are located by `lng`
+    /// and `lat` columns instead of `edge_id` and `distance`, snapped to
+    /// the nearest edge of `index`.
+    pub fn read_object_csv_snapped(
+        &self,
+        path: &Path,
+        index: &SpatialIndex,
+    ) -> Result<Vec<Arc<DataObject>>, ReaderError> {
+        self.read_objects(path, |record| {
+            let lng = Self::parse::<f32>(path, record, 2)?;
+            let lat = Self::parse::<f32>(path, record, 3)?;
+            let snap = index
+                .snap(lng, lat)
+                .ok_or_else(|| Self::invalid_value(path, record, 2))?;
+            Ok((snap.edge_id, snap.ratio))
+        })
+    }
+
+    /// Whether objects of a file in the object format are located by `lng`
+    /// and `lat` columns.
+    pub fn has_coordinates(&self, path: &Path) -> Result<bool, ReaderError> {
+        let mut reader = Self::open(path)?;
+        let headers = reader.headers().map_err(|source| ReaderError::Csv {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(headers.get(2) == Some("lng") && headers.get(3) == Some("lat"))
+    }
+
+    /// Read a file in the object format, `locate` giving the edge and
+    /// normalized distance of each record.
+    fn read_objects<F>(&self, path: &Path, locate: F) -> Result<Vec<Arc<DataObject>>, ReaderError>
+    where
+        F: Fn(&StringRecord) -> Result<(EdgeId, f32), ReaderError>,
+    {
         let mut vec = Vec::new();
 
-        let mut rdr = ReaderBuilder::new()
-            .delimiter(b' ')
-            .from_path(config.paths.object_path.as_path())
-            .unwrap();
-
-        for result in rdr.records() {
-            let record = result.unwrap();
-            let action = record.get(0).unwrap().parse::<i32>().unwrap();
-            let id = record.get(1).unwrap().parse::<ObjectId>().unwrap();
-            let edge_id = record.get(2).unwrap().parse::<EdgeId>().unwrap();
-            let dist = record.get(3).unwrap().parse::<f32>().unwrap();
+        for result in Self::open(path)?.records() {
+            let record = Self::record(path, result)?;
+            let action = Self::parse::<i32>(path, &record, 0)?;
+            let id = Self::parse::<ObjectId>(path, &record, 1)?;
+            let (edge_id, dist) = locate(&record)?;
+            // a fraction of the edge length, false for NaN as well
+            if !(0.0..=1.0).contains(&dist) {
+                return Err(Self::invalid_value(path, &record, 3));
+            }
             let mut attr = Vec::new();
             for i in 0..self.config.max_dim {
-                let val = record.get((4 + i).into()).unwrap().parse::<f32>().unwrap();
+                let val = Self::parse::<f32>(path, &record, (4 + i).into())?;
                 attr.push(val);
             }
             let action = if action == 1 {
@@ -50,115 +101,319 @@ impl Reader {
             vec.push(new_object);
         }
 
-        vec
+        Ok(vec)
     }
 
-    pub fn read_node_csv(&self) -> Vec<Arc<DataNode>> {
+    /// Number of attribute columns in the header of an object file.
+    pub fn object_dimensions(&self, path: &Path) -> Result<usize, ReaderError> {
+        let mut reader = Self::open(path)?;
+        let headers = reader.headers().map_err(|source| ReaderError::Csv {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(headers
+            .iter()
+            .filter(|h| !h.is_empty())
+            .count()
+            .saturating_sub(4))
+    }
+
+    /// Preference of each dimension, from the config or else from the
+    /// object file header, where an attribute column may be suffixed with
+    /// `:min` or `:max`. Dimensions default to `Preference::Max`.
+    pub fn preferences(&self) -> Result<Vec<Preference>, ReaderError> {
+        if !self.config.preferences.is_empty() {
+            return Ok(self.config.preferences.clone());
+        }
+
+        let path = self.config.paths.object_path.as_path();
+        let mut preferences = vec![Preference::Max; self.config.max_dim as usize];
+        if !path.is_file() {
+            return Ok(preferences);
+        }
+
+        let mut reader = Self::open(path)?;
+        let headers = reader.headers().map_err(|source| ReaderError::Csv {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        for (i, preference) in preferences.iter_mut().enumerate() {
+            let index = 4 + i;
+            if let Some((_, suffix)) = headers.get(index).and_then(|h| h.rsplit_once(':')) {
+                *preference = suffix
+                    .parse()
+                    .map_err(|_| Self::invalid_value(path, headers, index))?;
+            }
+        }
+        Ok(preferences)
+    }
+
+    pub fn read_node_csv(&self) -> Result<Vec<Arc<DataNode>>, ReaderError> {
+        let path = self.config.paths.node_path.as_path();
         let mut vec = Vec::new();
-        let mut rdr = ReaderBuilder::new()
-            .delimiter(b' ')
-            .from_path(self.config.paths.node_path.as_path())
-            .unwrap();
-
-        for result in rdr.records() {
-            let record = result.unwrap();
-            let id = record
-                .get(0)
-                .expect("Failed to get index 0")
-                .parse::<NodeId>()
-                .expect("Failed to parse Node ID");
-            let lng = record
-                .get(1)
-                .expect("Failed to get index 1")
-                .parse::<f32>()
-                .expect("Failed to parse lng");
-            let lat = record
-                .get(2)
-                .expect("Failed to get index 2")
-                .parse::<f32>()
-                .expect("Failed to parse lat");
+
+        for result in Self::open(path)?.records() {
+            let record = Self::record(path, result)?;
+            let id = Self::parse::<NodeId>(path, &record, 0)?;
+            let lng = Self::parse::<f32>(path, &record, 1)?;
+            let lat = Self::parse::<f32>(path, &record, 2)?;
             vec.push(Arc::new(DataNode { id, lng, lat }));
         }
         vec.sort_by(|a, b| a.id.partial_cmp(&b.id).unwrap());
-        vec
+        Ok(vec)
     }
 
-    pub fn read_edge_csv(&self, nodes: &Vec<Arc<DataNode>>) -> Vec<Arc<DataEdge>> {
+    /// Read edges. The edge file may have weight columns after `node_j`,
+    /// e.g. `id node_i node_j length time`. The edge length is taken from
+    /// the column named by `AppConfig::weight`, else from node coordinates,
+    /// whatever other columns there are. A column named `oneway` marks
+    /// edges traversable from node i to node j only with `1`.
+    ///
+    /// No two edges may join the same nodes, but for a pair of one-way edges
+    /// in opposite directions, e.g. a street open both ways whose lanes
+    /// differ in length.
+    pub fn read_edge_csv(
+        &self,
+        nodes: &[Arc<DataNode>],
+    ) -> Result<Vec<Arc<DataEdge>>, ReaderError> {
+        let path = self.config.paths.edge_path.as_path();
+        let weight = self.weight_column()?;
+        let oneway = self
+            .edge_columns()?
+            .into_iter()
+            .find(|(_, name)| name == ONEWAY)
+            .map(|(index, _)| index);
         let mut vec = Vec::new();
+        let mut map_pair_edges: HashMap<(NodeId, NodeId), Vec<Arc<DataEdge>>> = HashMap::new();
+
+        for result in Self::open(path)?.records() {
+            let record = Self::record(path, result)?;
+            let id = Self::parse::<EdgeId>(path, &record, 0)?;
+            let ni = Self::node(path, &record, 1, nodes)?;
+            let nj = Self::node(path, &record, 2, nodes)?;
+
+            let mut edge = match weight {
+                Some(index) => {
+                    let len = Self::parse::<f32>(path, &record, index)?;
+                    if !(len >= 0.0 && len.is_finite()) {
+                        return Err(Self::invalid_value(path, &record, index));
+                    }
+                    DataEdge {
+                        id,
+                        ni: ni.id,
+                        nj: nj.id,
+                        len,
+                        oneway: false,
+                    }
+                }
+                None => DataEdge::with_distance(id, ni, nj, self.config.distance),
+            };
+            if let Some(index) = oneway {
+                edge.oneway = match Self::parse::<u8>(path, &record, index)? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(Self::invalid_value(path, &record, index)),
+                };
+            }
 
-        let mut rdr = ReaderBuilder::new()
-            .delimiter(b' ')
-            .from_path(self.config.paths.edge_path.as_path())
-            .unwrap();
-
-        for result in rdr.records() {
-            let record = result.unwrap();
-            let id = record
-                .get(0)
-                .expect("Failed to get index 0")
-                .parse::<EdgeId>()
-                .expect("Failed to parse Edge ID");
-            let ni_id = record
-                .get(1)
-                .expect("Failed to get index 1")
-                .parse::<NodeId>()
-                .expect("Failed to parse node i id");
-            let nj_id = record
-                .get(2)
-                .expect("Failed to get index 2")
-                .parse::<NodeId>()
-                .expect("Failed to parse node j id");
-
-            let index_ni = nodes.binary_search_by(|n| n.id.cmp(&ni_id)).unwrap();
-            let index_nj = nodes.binary_search_by(|n| n.id.cmp(&nj_id)).unwrap();
-            let ni = nodes.get(index_ni).unwrap();
-            let nj = nodes.get(index_nj).unwrap();
-
-            vec.push(Arc::new(DataEdge::new(id, ni.clone(), nj.clone())));
+            // a single edge between two nodes, or one edge each way
+            let pair = (edge.ni.min(edge.nj), edge.ni.max(edge.nj));
+            let pair_edges = map_pair_edges.entry(pair).or_default();
+            if let Some(other) = pair_edges.first() {
+                let is_opposite = pair_edges.len() == 1
+                    && edge.oneway
+                    && other.oneway
+                    && edge.ni != edge.nj
+                    && edge.ni == other.nj;
+                if !is_opposite {
+                    return Err(ReaderError::DuplicateEdge {
+                        path: path.to_path_buf(),
+                        line: Self::line(&record),
+                        edge_id: id,
+                        other_id: other.id,
+                    });
+                }
+            }
+            let edge = Arc::new(edge);
+            pair_edges.push(edge.clone());
+            vec.push(edge);
         }
 
         vec.sort_by(|a, b| a.id.partial_cmp(&b.id).unwrap());
-        vec
+        Ok(vec)
     }
 
-    pub fn read_query_csv(&self) -> Vec<Arc<Query>> {
-        let mut vec = Vec::new();
+    /// Index and name of the columns of the edge file after `node_j`.
+    fn edge_columns(&self) -> Result<Vec<(usize, String)>, ReaderError> {
+        let path = self.config.paths.edge_path.as_path();
+        let mut reader = Self::open(path)?;
+        let headers = reader.headers().map_err(|source| ReaderError::Csv {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(headers
+            .iter()
+            .enumerate()
+            .skip(3)
+            .filter(|(_, h)| !h.is_empty())
+            .map(|(i, h)| (i, String::from(h)))
+            .collect())
+    }
 
-        let mut rdr = ReaderBuilder::new()
-            .delimiter(b' ')
-            .from_path(self.config.paths.query_path.as_path())
-            .unwrap();
+    /// Names of the weight columns of the edge file.
+    pub fn edge_weights(&self) -> Result<Vec<String>, ReaderError> {
+        Ok(self
+            .edge_columns()?
+            .into_iter()
+            .map(|(_, name)| name)
+            .filter(|name| name != ONEWAY)
+            .collect())
+    }
 
-        let mut id = 0;
-        for result in rdr.records() {
-            id += 1;
+    /// Index of the edge file column which drives edge lengths, see
+    /// `read_edge_csv`.
+    pub(crate) fn weight_column(&self) -> Result<Option<usize>, ReaderError> {
+        let mut weights = self
+            .edge_columns()?
+            .into_iter()
+            .filter(|(_, name)| name != ONEWAY);
+        match &self.config.weight {
+            Some(weight) => match weights.find(|(_, name)| name == weight) {
+                Some((index, _)) => Ok(Some(index)),
+                None => Err(ReaderError::UnknownColumn {
+                    path: self.config.paths.edge_path.clone(),
+                    name: weight.clone(),
+                }),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn read_query_csv(&self) -> Result<Vec<Arc<Query>>, ReaderError> {
+        let path = self.config.paths.query_path.as_path();
+        let default_preferences = self.preferences()?;
+        let mut vec = Vec::new();
 
-            let record = result.unwrap();
-            let k = record
-                .get(0)
-                .expect("Failed to get index 0")
-                .parse::<K>()
-                .expect("Failed to get k value");
+        for (id, result) in (1..).zip(Self::open(path)?.records()) {
+            let record = Self::record(path, result)?;
+            let k = Self::parse::<K>(path, &record, 0)?;
 
-            let mut dimensions = Vec::new();
+            // dimension, optionally suffixed with `:min` or `:max`
+            let mut criteria = Vec::new();
             let mut i = 1;
             while let Some(str) = record.get(i) {
                 if str.is_empty() {
                     break;
                 }
 
-                let d = str
+                let (d, preference) = match str.split_once(':') {
+                    Some((d, p)) => (d, Some(p)),
+                    None => (str, None),
+                };
+                let d = d
                     .parse::<DimensionIndex>()
-                    .expect("Failed to parse dimension");
-                dimensions.push(d);
+                    .map_err(|_| Self::invalid_value(path, &record, i))?;
+                if d == 0 || d > self.config.max_dim {
+                    return Err(Self::invalid_value(path, &record, i));
+                }
+                let preference = match preference {
+                    Some(p) => p
+                        .parse::<Preference>()
+                        .map_err(|_| Self::invalid_value(path, &record, i))?,
+                    None => *default_preferences
+                        .get((d - 1) as usize)
+                        .ok_or_else(|| Self::invalid_value(path, &record, i))?,
+                };
+                criteria.push((d, preference));
                 i += 1;
             }
-            dimensions.sort();
-            let query = Query { id, k, dimensions };
+            criteria.sort();
+            let (dimensions, preferences) = criteria.into_iter().unzip();
+            let query = Query {
+                id,
+                k,
+                dimensions,
+                preferences,
+            };
+            UpdateEngine::check_query(&query, self.config.max_dim).map_err(|source| {
+                ReaderError::InvalidQuery {
+                    path: path.to_path_buf(),
+                    line: Self::line(&record),
+                    source,
+                }
+            })?;
             vec.push(Arc::new(query));
         }
 
-        vec
+        Ok(vec)
+    }
+
+    fn open(path: &Path) -> Result<csv::Reader<std::fs::File>, ReaderError> {
+        ReaderBuilder::new()
+            .delimiter(b' ')
+            .from_path(path)
+            .map_err(|source| ReaderError::Csv {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    pub(crate) fn record(
+        path: &Path,
+        result: Result<StringRecord, csv::Error>,
+    ) -> Result<StringRecord, ReaderError> {
+        result.map_err(|source| ReaderError::Csv {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Parse value of the `index`-th field of `record`.
+    pub(crate) fn parse<T: FromStr>(
+        path: &Path,
+        record: &StringRecord,
+        index: usize,
+    ) -> Result<T, ReaderError> {
+        let value = record.get(index).ok_or_else(|| ReaderError::MissingField {
+            path: path.to_path_buf(),
+            line: Self::line(record),
+            column: index + 1,
+        })?;
+        value
+            .parse::<T>()
+            .map_err(|_| Self::invalid_value(path, record, index))
+    }
+
+    /// Look up the node referred by the `index`-th field of `record`.
+    fn node(
+        path: &Path,
+        record: &StringRecord,
+        index: usize,
+        nodes: &[Arc<DataNode>],
+    ) -> Result<Arc<DataNode>, ReaderError> {
+        let node_id = Self::parse::<NodeId>(path, record, index)?;
+        match nodes.binary_search_by(|n| n.id.cmp(&node_id)) {
+            Ok(i) => Ok(nodes[i].clone()),
+            Err(_) => Err(ReaderError::UnknownNode {
+                path: path.to_path_buf(),
+                line: Self::line(record),
+                column: index + 1,
+                node_id,
+            }),
+        }
+    }
+
+    pub(crate) fn invalid_value(path: &Path, record: &StringRecord, index: usize) -> ReaderError {
+        ReaderError::InvalidValue {
+            path: path.to_path_buf(),
+            line: Self::line(record),
+            column: index + 1,
+            value: record.get(index).unwrap_or_default().to_string(),
+        }
+    }
+
+    fn line(record: &StringRecord) -> u64 {
+        record.position().map(|p| p.line()).unwrap_or(0)
     }
 }
 
@@ -171,9 +426,9 @@ mod tests {
         let conf: AppConfig = Default::default();
         let conf = Arc::new(conf);
         let reader = Reader::new(conf);
-        let objects = reader.read_object_csv();
+        let objects = reader.read_object_csv().unwrap();
 
-        let o1: &Arc<DataObject> = objects.get(0).unwrap();
+        let o1: &Arc<DataObject> = objects.first().unwrap();
         let o2: &Arc<DataObject> = objects.get(1).unwrap();
         assert_eq!(o1.id, 1);
         assert_eq!(o2.id, 2);
@@ -184,17 +439,17 @@ mod tests {
         let conf: AppConfig = Default::default();
         let conf = Arc::new(conf);
         let reader = Reader::new(conf);
-        let nodes = reader.read_node_csv();
+        let nodes = reader.read_node_csv().unwrap();
 
-        let n1 = nodes.get(0).unwrap();
+        let n1 = nodes.first().unwrap();
         let n2 = nodes.get(1).unwrap();
 
         assert_eq!(n1.id, 1);
         assert_eq!(n2.id, 2);
 
-        let edges = reader.read_edge_csv(&nodes);
+        let edges = reader.read_edge_csv(&nodes).unwrap();
 
-        let e1 = edges.get(0).unwrap();
+        let e1 = edges.first().unwrap();
         let e2 = edges.get(1).unwrap();
 
         assert_eq!(e1.id, 1);
@@ -206,12 +461,294 @@ mod tests {
         let conf: AppConfig = Default::default();
         let conf = Arc::new(conf);
         let reader = Reader::new(conf);
-        let queries = reader.read_query_csv();
+        let queries = reader.read_query_csv().unwrap();
 
-        let q1 = queries.get(0).unwrap();
+        let q1 = queries.first().unwrap();
         let q2 = queries.get(1).unwrap();
 
         assert_eq!(q1.k, 3);
         assert_eq!(q2.k, 4);
     }
+
+    #[test]
+    fn read_invalid_query_csv() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let conf = AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            ..Default::default()
+        };
+        let reader = Reader::new(Arc::new(conf));
+
+        let cases = ["k d1 d2\n2 1 2\n3 1 2\n", "k d1 d2\n2 1 2\n0 1 2\n"];
+        for csv in cases {
+            std::fs::write(dir.join("query.txt"), csv).unwrap();
+            match reader.read_query_csv() {
+                Err(ReaderError::InvalidQuery {
+                    line,
+                    source: UpdateError::InvalidQuery(query_id),
+                    ..
+                }) => assert_eq!((line, query_id), (3, 2)),
+                r => panic!("unexpected {:?}", r),
+            }
+        }
+
+        std::fs::write(dir.join("query.txt"), "k d1 d2 d3\n2 3 1 3:min\n").unwrap();
+        match reader.read_query_csv() {
+            Err(ReaderError::InvalidQuery {
+                line,
+                source:
+                    UpdateError::DuplicateDimension {
+                        query_id,
+                        dimension,
+                    },
+                ..
+            }) => assert_eq!((line, query_id, dimension), (2, 1, 3)),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn read_invalid_csv() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("node.txt"), "id lng lat\n1 0.0 0.0\n2 1.0 x\n").unwrap();
+        std::fs::write(dir.join("edge.txt"), "id node_i node_j\n1 1 2\n2 1 9\n").unwrap();
+
+        let conf = AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            ..Default::default()
+        };
+        let reader = Reader::new(Arc::new(conf));
+
+        match reader.read_node_csv() {
+            Err(ReaderError::InvalidValue {
+                line,
+                column,
+                value,
+                ..
+            }) => {
+                assert_eq!((line, column), (3, 3));
+                assert_eq!(value, "x");
+            }
+            r => panic!("unexpected {:?}", r),
+        }
+
+        let nodes = vec![
+            Arc::new(DataNode {
+                id: 1,
+                lng: 0.0,
+                lat: 0.0,
+            }),
+            Arc::new(DataNode {
+                id: 2,
+                lng: 1.0,
+                lat: 0.0,
+            }),
+        ];
+        match reader.read_edge_csv(&nodes) {
+            Err(ReaderError::UnknownNode {
+                line,
+                column,
+                node_id,
+                ..
+            }) => assert_eq!((line, column, node_id), (3, 3, 9)),
+            r => panic!("unexpected {:?}", r),
+        }
+
+        // distances are fractions of the edge length
+        for dist in ["NaN", "-0.1", "1.5"] {
+            let object = dir.join("object.txt");
+            let csv = format!(
+                "action id edge_id distance d1 d2 d3 d4\n1 1 1 0.5 1 2 3 4\n1 2 1 {} 1 2 3 4\n",
+                dist
+            );
+            std::fs::write(&object, csv).unwrap();
+            match reader.read_object_csv_at(&object) {
+                Err(ReaderError::InvalidValue {
+                    line,
+                    column,
+                    value,
+                    ..
+                }) => {
+                    assert_eq!((line, column), (3, 4));
+                    assert_eq!(value, dist);
+                }
+                r => panic!("unexpected {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn read_preferences() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("object.txt"),
+            "action id edge_id distance price:min d2 rating:max d4\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("query.txt"), "k d1 d2\n1 1 2:min\n2 3:min 1\n").unwrap();
+
+        let conf = AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            ..Default::default()
+        };
+        let reader = Reader::new(Arc::new(conf));
+        use Preference::*;
+        assert_eq!(reader.preferences().unwrap(), vec![Min, Max, Max, Max]);
+
+        let queries = reader.read_query_csv().unwrap();
+        assert_eq!(queries[0].dimensions, vec![1, 2]);
+        assert_eq!(queries[0].preferences, vec![Min, Min]);
+        assert_eq!(queries[1].dimensions, vec![1, 3]);
+        assert_eq!(queries[1].preferences, vec![Min, Min]);
+
+        // configured preferences cover fewer dimensions than max_dim
+        std::fs::write(dir.join("query.txt"), "k d1 d2\n1 1 2\n").unwrap();
+        let conf = AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            preferences: vec![Min],
+            ..Default::default()
+        };
+        let reader = Reader::new(Arc::new(conf));
+        match reader.read_query_csv() {
+            Err(ReaderError::InvalidValue {
+                line,
+                column,
+                value,
+                ..
+            }) => {
+                assert_eq!((line, column), (2, 3));
+                assert_eq!(value, "2");
+            }
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn read_edge_haversine() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("node.txt"),
+            "id lng lat\n1 -121.904167 41.974556\n2 -121.902153 41.974766\n3 -121.904167 42.974556\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("edge.txt"), "id node_i node_j\n1 1 2\n2 1 3\n").unwrap();
+
+        let conf = AppConfig {
+            distance: DistanceModel::Haversine,
+            paths: Paths::new(dir.to_path_buf()),
+            ..Default::default()
+        };
+        let reader = Reader::new(Arc::new(conf));
+        let nodes = reader.read_node_csv().unwrap();
+        let edges = reader.read_edge_csv(&nodes).unwrap();
+
+        // Edge 0 of cal.cedge.txt
+        assert!((edges[0].len - 168.0).abs() < 1.0, "{}", edges[0].len);
+        // One degree of latitude
+        assert!((edges[1].len - 111_195.0).abs() < 5.0, "{}", edges[1].len);
+    }
+
+    #[test]
+    fn read_edge_weights() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("node.txt"),
+            "id lng lat\n1 0.0 0.0\n2 3.0 4.0\n3 3.0 0.0\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("edge.txt"),
+            "id node_i node_j length time\n1 1 2 7.5 2\n",
+        )
+        .unwrap();
+
+        let mut conf = AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            ..Default::default()
+        };
+        let reader = Reader::new(Arc::new(conf.clone()));
+        assert_eq!(reader.edge_weights().unwrap(), vec!["length", "time"]);
+        let nodes = reader.read_node_csv().unwrap();
+        // weight columns are used only if named
+        assert_eq!(reader.read_edge_csv(&nodes).unwrap()[0].len, 5.0);
+
+        conf.weight = Some(String::from("length"));
+        let reader = Reader::new(Arc::new(conf.clone()));
+        assert_eq!(reader.read_edge_csv(&nodes).unwrap()[0].len, 7.5);
+
+        conf.weight = Some(String::from("time"));
+        let reader = Reader::new(Arc::new(conf.clone()));
+        assert_eq!(reader.read_edge_csv(&nodes).unwrap()[0].len, 2.0);
+
+        conf.weight = Some(String::from("cost"));
+        let reader = Reader::new(Arc::new(conf.clone()));
+        assert!(matches!(
+            reader.read_edge_csv(&nodes),
+            Err(ReaderError::UnknownColumn { .. })
+        ));
+
+        std::fs::write(
+            dir.join("edge.txt"),
+            "id node_i node_j oneway length\n1 1 2 1 6\n2 2 3 0 6\n",
+        )
+        .unwrap();
+        conf.weight = Some(String::from("length"));
+        let reader = Reader::new(Arc::new(conf.clone()));
+        assert_eq!(reader.edge_weights().unwrap(), vec!["length"]);
+        let edges = reader.read_edge_csv(&nodes).unwrap();
+        assert_eq!((edges[0].len, edges[0].oneway), (6.0, true));
+        assert!(!edges[1].oneway);
+
+        // a column which is not a weight does not change lengths
+        std::fs::write(
+            dir.join("edge.txt"),
+            "id node_i node_j class\n1 1 2 3\n2 2 3 1\n",
+        )
+        .unwrap();
+        conf.weight = None;
+        let reader = Reader::new(Arc::new(conf.clone()));
+        let edges = reader.read_edge_csv(&nodes).unwrap();
+        assert_eq!((edges[0].len, edges[1].len), (5.0, 4.0));
+
+        std::fs::write(dir.join("edge.txt"), "id node_i node_j\n1 1 2\n").unwrap();
+        let reader = Reader::new(Arc::new(conf));
+        assert_eq!(reader.read_edge_csv(&nodes).unwrap()[0].len, 5.0);
+    }
+
+    #[test]
+    fn read_object_coordinates() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("node.txt"),
+            "id lng lat\n1 0.0 0.0\n2 10.0 0.0\n3 10.0 10.0\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("edge.txt"), "id node_i node_j\n1 1 2\n2 2 3\n").unwrap();
+        std::fs::write(
+            dir.join("object.txt"),
+            "action id lng lat d1 d2 d3 d4\n1 1 2.5 0.5 1 2 3 4\n1 2 11.0 8.0 4 3 2 1\n",
+        )
+        .unwrap();
+
+        let conf = Arc::new(AppConfig {
+            paths: Paths::new(dir.to_path_buf()),
+            ..Default::default()
+        });
+        let reader = Reader::new(conf.clone());
+        let path = conf.paths.object_path.as_path();
+        assert!(reader.has_coordinates(path).unwrap());
+
+        let graph = Graph::new(conf).unwrap();
+        let o1 = graph.object(1);
+        assert_eq!((o1.edge_id, o1.dist), (1, 0.25));
+        let o2 = graph.object(2);
+        assert_eq!((o2.edge_id, o2.dist), (2, 0.8));
+        assert_eq!(o2.attr, vec![4.0, 3.0, 2.0, 1.0]);
+    }
 }
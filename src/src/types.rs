@@ -1,7 +1,12 @@
+use serde::Deserialize;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::prelude::*;
 
+/// Mean radius of the earth in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
 /// Raw node data from dataset
 #[derive(Debug)]
 pub struct DataNode {
@@ -17,19 +22,111 @@ pub struct DataEdge {
     pub ni: NodeId,
     pub nj: NodeId,
     pub len: f32,
+    /// Traversable from node i to node j only
+    pub oneway: bool,
+}
+
+/// How the length of an edge follows from the coordinates of its nodes.
+/// `max_dist` is in the same unit.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceModel {
+    /// Straight line on the plane, for normalized coordinates
+    #[default]
+    Euclidean,
+    /// Great-circle distance in meters, for WGS84 degrees
+    Haversine,
+}
+
+impl DistanceModel {
+    /// Distance between two nodes
+    pub fn distance(self, a: &DataNode, b: &DataNode) -> f32 {
+        match self {
+            DistanceModel::Euclidean => {
+                let diff_lng = a.lng - b.lng;
+                let diff_lat = a.lat - b.lat;
+                (diff_lng * diff_lng + diff_lat * diff_lat).sqrt()
+            }
+            DistanceModel::Haversine => {
+                let (lat_a, lat_b) = ((a.lat as f64).to_radians(), (b.lat as f64).to_radians());
+                let half_lat = (lat_b - lat_a) / 2.0;
+                let half_lng = (b.lng as f64 - a.lng as f64).to_radians() / 2.0;
+                let h = half_lat.sin().powi(2) + lat_a.cos() * lat_b.cos() * half_lng.sin().powi(2);
+                (2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()) as f32
+            }
+        }
+    }
+
+    /// Distance per degree of longitude and of latitude around latitude
+    /// `lat`, to measure short distances on the plane.
+    pub fn units_per_degree(self, lat: f32) -> (f32, f32) {
+        match self {
+            DistanceModel::Euclidean => (1.0, 1.0),
+            DistanceModel::Haversine => {
+                // length of the arc of one degree
+                let per_degree = EARTH_RADIUS.to_radians();
+                let per_degree_lng = per_degree * (lat as f64).to_radians().cos();
+                (per_degree_lng as f32, per_degree as f32)
+            }
+        }
+    }
+}
+
+impl FromStr for DistanceModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "euclidean" => Ok(DistanceModel::Euclidean),
+            "haversine" => Ok(DistanceModel::Haversine),
+            _ => Err(format!("unknown distance model {}", s)),
+        }
+    }
+}
+
+/// Which way the network distance of a zone is measured on one-way edges
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Distance driven from the object to the location
+    #[default]
+    FromObject,
+    /// Distance driven from the location to the object
+    ToObject,
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "from_object" => Ok(Direction::FromObject),
+            "to_object" => Ok(Direction::ToObject),
+            _ => Err(format!("unknown direction {}", s)),
+        }
+    }
 }
 
 impl DataEdge {
-    /// Create new raw edge
+    /// Create new raw edge, with Euclidean length
     pub fn new(id: EdgeId, ni: Arc<DataNode>, nj: Arc<DataNode>) -> DataEdge {
-        let diff_lng = ni.lng - nj.lng;
-        let diff_lat = ni.lat - nj.lat;
-        let len = (diff_lng * diff_lng + diff_lat * diff_lat).sqrt();
+        Self::with_distance(id, ni, nj, DistanceModel::Euclidean)
+    }
+
+    /// Create new raw edge, with length in the unit of `distance`
+    pub fn with_distance(
+        id: EdgeId,
+        ni: Arc<DataNode>,
+        nj: Arc<DataNode>,
+        distance: DistanceModel,
+    ) -> DataEdge {
+        let len = distance.distance(&ni, &nj);
         DataEdge {
             id,
             ni: ni.id,
             nj: nj.id,
             len,
+            oneway: false,
         }
     }
 }
@@ -57,4 +154,6 @@ pub struct Query {
     pub id: QueryId,
     pub k: K,
     pub dimensions: Vec<DimensionIndex>,
+    /// Preference of each of `dimensions`
+    pub preferences: Vec<Preference>,
 }
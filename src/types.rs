@@ -1,13 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
 pub type DimensionIndex = u8;
 pub type ObjectId = u32;
 pub type EdgeId = u32;
 pub type NodeId = u32;
 pub type K = u8;
 pub type QueryId = u32;
-pub type CentroidId = NodeId;
+pub type CentroidId = ObjectId;
+
+/// Which values of a dimension are better
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preference {
+    /// Larger value wins, e.g. rating
+    Max,
+    /// Smaller value wins, e.g. price
+    Min,
+}
+
+impl Preference {
+    /// Whether `a` is strictly better than `b`
+    pub fn is_better(self, a: f32, b: f32) -> bool {
+        match self {
+            Preference::Max => a > b,
+            Preference::Min => a < b,
+        }
+    }
+}
+
+impl FromStr for Preference {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(Preference::Max),
+            "min" => Ok(Preference::Min),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Node of the traversed graph. Objects become nodes of their own when
+/// their edge is split, so they never collide with dataset node ids.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GraphNodeId {
+    /// Node from the dataset
+    Original(NodeId),
+    /// Object split into its edge
+    Object(ObjectId),
+}
+
+/// Edge of the traversed graph. Splitting an edge at its objects replaces
+/// it with one edge before each object plus one after the last object.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GraphEdgeId {
+    /// Edge from the dataset
+    Original(EdgeId),
+    /// Edge ending at the object, starting at the previous object or node i
+    BeforeObject(ObjectId),
+    /// Edge from the last object of a split edge to node j
+    AfterObject(ObjectId),
+}
 
 pub use crate::ik::al::bfs_mh::{BfsMinHeap, TraverseState};
 pub use crate::ik::al::vor::{DomTraverse, Range, Voronoi};
+pub use crate::ik::builder::ZoneBuilder;
+pub use crate::ik::engine::{UpdateEngine, UpdateError};
+pub use crate::ik::lattice::{Comparisons, SubspaceLattice};
+pub use crate::ik::oracle::{Mismatch, Oracle};
 pub use crate::ik::st::edge::Edge;
 pub use crate::ik::st::node::Node;
-pub use crate::ik::st::result::ResultVoronoi;
+pub use crate::ik::st::result::{Location, ResultVoronoi, ZoneKey};
+pub use crate::ik::st::snapshot::{SnapshotError, SnapshotFormat};
+pub use crate::ik::st::spatial::{Snap, SpatialIndex};
+pub use crate::ik::st::stats::{LengthSummary, Stats, ZoneSize, ZoneStats};
+pub use crate::ik::st::view::GraphView;
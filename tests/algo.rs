@@ -1,6 +1,314 @@
 extern crate t;
 
+use std::fmt::Write;
+use std::fs;
+use std::sync::Arc;
+use t::*;
+use tempfile::TempDir;
+
+/// Linear congruential generator, enough to build reproducible graphs.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as u32
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        self.next() % n
+    }
+
+    fn unit(&mut self) -> f32 {
+        self.next() as f32 / (1u64 << 31) as f32
+    }
+}
+
+/// Write a jittered `size` x `size` grid with random objects to a temporary
+/// dataset directory, with some edges one-way if `oneway`.
+fn random_dataset(
+    seed: u64,
+    size: u32,
+    object_count: u32,
+    max_dim: DimensionIndex,
+    oneway: bool,
+) -> TempDir {
+    let mut rng = Lcg(seed);
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut nodes = String::from("id lng lat\n");
+    for i in 0..size * size {
+        let x = (i % size) as f32 * 40.0 + rng.unit() * 20.0;
+        let y = (i / size) as f32 * 40.0 + rng.unit() * 20.0;
+        writeln!(nodes, "{} {} {}", i + 1, x, y).unwrap();
+    }
+
+    let mut edges = String::from(if oneway {
+        "id node_i node_j oneway\n"
+    } else {
+        "id node_i node_j\n"
+    });
+    let mut edge_id = 0;
+    for i in 0..size * size {
+        let right = i % size + 1 < size;
+        let down = i / size + 1 < size;
+        for (has_next, next) in &[(right, i + 1), (down, i + size)] {
+            // drop some edges, the graph may become disconnected
+            if *has_next && rng.below(5) != 0 {
+                edge_id += 1;
+                if oneway {
+                    // half of the edges one-way, in either direction
+                    let (ni, nj) = if rng.below(2) == 0 {
+                        (i + 1, next + 1)
+                    } else {
+                        (next + 1, i + 1)
+                    };
+                    writeln!(edges, "{} {} {} {}", edge_id, ni, nj, rng.below(2)).unwrap();
+                } else {
+                    writeln!(edges, "{} {} {}", edge_id, i + 1, next + 1).unwrap();
+                }
+            }
+        }
+    }
+
+    let objects = random_objects(&mut rng, edge_id, object_count, max_dim);
+
+    fs::write(dir.path().join("node.txt"), nodes).unwrap();
+    fs::write(dir.path().join("edge.txt"), edges).unwrap();
+    fs::write(dir.path().join("object.txt"), objects).unwrap();
+    dir
+}
+
+/// Object file of `object_count` random objects on edges `1..=edge_count`.
+fn random_objects(
+    rng: &mut Lcg,
+    edge_count: u32,
+    object_count: u32,
+    max_dim: DimensionIndex,
+) -> String {
+    let mut objects = String::from("action id edge_id distance");
+    for d in 1..=max_dim {
+        write!(objects, " d{}", d).unwrap();
+    }
+    objects.push('\n');
+    for id in 1..=object_count {
+        let edge = rng.below(edge_count) + 1;
+        write!(objects, "1 {} {} {}", id, edge, rng.unit()).unwrap();
+        for _ in 0..max_dim {
+            // few distinct values, so ties are common
+            write!(objects, " {}", rng.below(5)).unwrap();
+        }
+        objects.push('\n');
+    }
+    objects
+}
+
+/// One query of every k over `dimensions`
+fn group(dimensions: &[DimensionIndex], preferences: &[Preference]) -> Vec<Arc<Query>> {
+    let max_k = dimensions.len() as K;
+    (1..=max_k)
+        .map(|k| {
+            Arc::new(Query {
+                id: k as QueryId,
+                k,
+                dimensions: dimensions.to_vec(),
+                preferences: preferences.to_vec(),
+            })
+        })
+        .collect()
+}
+
+fn queries(dimensions: &[DimensionIndex], preferences: &[Preference]) -> Queries {
+    Queries::new(group(dimensions, preferences))
+}
+
+fn assert_matches_oracle(graph: &Graph, queries: &Queries) {
+    let result = ZoneBuilder::new(graph, queries).build();
+    let oracle = Oracle::new(graph);
+    for group in queries.iter() {
+        let mismatches = oracle.compare(&result, group, 20, 1e-2);
+        assert!(mismatches.is_empty(), "{:#?}", mismatches);
+    }
+}
+
+#[test]
+fn oracle_test01() {
+    let conf = Arc::new(AppConfig::default());
+    let reader = Reader::new(conf.clone());
+    let graph = Graph::new(conf).unwrap();
+    let queries = Queries::new(reader.read_query_csv().unwrap());
+    assert_matches_oracle(&graph, &queries);
+}
+
 #[test]
-fn hello() {
-    println!("Hello");
+fn oracle_random() {
+    for seed in 1..=20 {
+        let size = seed as u32 % 3 + 2;
+        let dir = random_dataset(seed, size, seed as u32 % 10 + 2, 4, false);
+        let conf = AppConfig {
+            max_dim: 4,
+            max_dist: [25.0, 60.0, 150.0][seed as usize % 3],
+            dataset_dir: dir.path().to_path_buf(),
+            paths: Paths::new(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let graph = Graph::new(Arc::new(conf)).unwrap();
+
+        let max = [Preference::Max; 4];
+        assert_matches_oracle(&graph, &queries(&[1, 2, 3, 4], &max));
+        assert_matches_oracle(&graph, &queries(&[1, 3], &max[..2]));
+
+        let mixed = [
+            Preference::Min,
+            Preference::Max,
+            Preference::Min,
+            Preference::Max,
+        ];
+        assert_matches_oracle(&graph, &queries(&[1, 2, 3, 4], &mixed));
+    }
+}
+
+#[test]
+fn oracle_oneway() {
+    for seed in 1..=20 {
+        let size = seed as u32 % 3 + 2;
+        let dir = random_dataset(seed, size, seed as u32 % 10 + 2, 4, true);
+        for direction in &[Direction::FromObject, Direction::ToObject] {
+            let conf = AppConfig {
+                max_dim: 4,
+                max_dist: [25.0, 60.0, 150.0][seed as usize % 3],
+                direction: *direction,
+                dataset_dir: dir.path().to_path_buf(),
+                paths: Paths::new(dir.path().to_path_buf()),
+                ..Default::default()
+            };
+            let graph = Graph::new(Arc::new(conf)).unwrap();
+
+            let max = [Preference::Max; 4];
+            assert_matches_oracle(&graph, &queries(&[1, 2, 3, 4], &max));
+            assert_matches_oracle(&graph, &queries(&[1, 3], &max[..2]));
+        }
+    }
+}
+
+#[test]
+fn oracle_opposite_oneway() {
+    // streets 1-2 and 3-4 open both ways, as one-way edges of different
+    // lengths
+    let nodes = "id lng lat\n1 0 0\n2 100 0\n3 100 100\n4 0 100\n";
+    let edges = "id node_i node_j length oneway\n\
+                 1 1 2 100 1\n2 2 1 40 1\n3 2 3 100 0\n4 3 4 100 1\n\
+                 5 4 3 160 1\n6 4 1 100 0\n7 1 3 150 0\n";
+    for seed in 1..=10 {
+        let mut rng = Lcg(seed);
+        let objects = random_objects(&mut rng, 7, seed as u32 % 6 + 2, 4);
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("node.txt"), nodes).unwrap();
+        fs::write(dir.path().join("edge.txt"), edges).unwrap();
+        fs::write(dir.path().join("object.txt"), objects).unwrap();
+
+        for direction in &[Direction::FromObject, Direction::ToObject] {
+            let conf = AppConfig {
+                max_dim: 4,
+                max_dist: [50.0, 120.0, 300.0][seed as usize % 3],
+                direction: *direction,
+                weight: Some(String::from("length")),
+                dataset_dir: dir.path().to_path_buf(),
+                paths: Paths::new(dir.path().to_path_buf()),
+                ..Default::default()
+            };
+            let graph = Graph::new(Arc::new(conf)).unwrap();
+
+            let max = [Preference::Max; 4];
+            assert_matches_oracle(&graph, &queries(&[1, 2, 3, 4], &max));
+            assert_matches_oracle(&graph, &queries(&[1, 3], &max[..2]));
+        }
+    }
+}
+
+#[test]
+fn oracle_generated() {
+    let distributions = [
+        Distribution::Independent,
+        Distribution::Correlated,
+        Distribution::AntiCorrelated,
+    ];
+    for (seed, distribution) in distributions.iter().enumerate() {
+        let dir = tempfile::tempdir().unwrap();
+        Generator::new(seed as u64)
+            .network(Network::Planar)
+            .size(4, 4)
+            .distribution(*distribution)
+            .dimensions(4)
+            .objects(10)
+            .generate()
+            .write(dir.path())
+            .unwrap();
+        let conf = Arc::new(AppConfig {
+            max_dim: 4,
+            max_dist: 15.0,
+            dataset_dir: dir.path().to_path_buf(),
+            paths: Paths::new(dir.path().to_path_buf()),
+            ..Default::default()
+        });
+        let reader = Reader::new(conf.clone());
+        let graph = Graph::new(conf).unwrap();
+        let queries = Queries::new(reader.read_query_csv().unwrap());
+        assert_matches_oracle(&graph, &queries);
+    }
+}
+
+#[test]
+fn lattice_matches_groups() {
+    use Preference::*;
+    let subspaces: [(&[DimensionIndex], &[Preference]); 5] = [
+        (&[1, 2, 3, 4], &[Max; 4]),
+        (&[1, 2, 3], &[Max; 3]),
+        (&[2], &[Max]),
+        (&[1, 2], &[Min, Max]),
+        (&[1, 2, 4], &[Min, Max, Min]),
+    ];
+
+    for seed in 1..=10 {
+        let dir = random_dataset(seed, seed as u32 % 3 + 2, seed as u32 % 10 + 2, 4, false);
+        let conf = AppConfig {
+            max_dim: 4,
+            max_dist: [25.0, 60.0, 150.0][seed as usize % 3],
+            dataset_dir: dir.path().to_path_buf(),
+            paths: Paths::new(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let graph = Graph::new(Arc::new(conf)).unwrap();
+
+        let all = Queries::new(subspaces.iter().flat_map(|(d, p)| group(d, p)).collect());
+        let result = ZoneBuilder::new(&graph, &all).build();
+
+        let mut expected = ResultVoronoi::from_edge_ids(graph.map_edges());
+        for (dimensions, preferences) in &subspaces {
+            let queries = queries(dimensions, preferences);
+            expected.merge(ZoneBuilder::new(&graph, &queries).build());
+        }
+
+        let mut locations = Vec::new();
+        for (edge_id, edge) in graph.map_edges() {
+            for i in 0..=20 {
+                locations.push(Location::Edge(edge_id, edge.len * i as f32 / 20.0));
+            }
+        }
+        for (dimensions, preferences) in &subspaces {
+            for k in 1..=dimensions.len() as K {
+                let key = ZoneKey::new(dimensions, preferences, k);
+                assert_eq!(
+                    result.locate_all(&locations, &key),
+                    expected.locate_all(&locations, &key)
+                );
+            }
+        }
+
+        // zones of subspaces sharing k are kept apart
+        assert_matches_oracle(&graph, &all);
+    }
 }